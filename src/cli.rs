@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -25,6 +25,13 @@ pub enum Commands {
 
         #[arg(short, long, help = "Base branch to rebase from")]
         base: Option<String>,
+
+        #[arg(
+            short = 'x',
+            long,
+            help = "Command to run after each commit, stopping at the first one it fails for"
+        )]
+        exec: Option<String>,
     },
 
     #[command(
@@ -32,11 +39,60 @@ pub enum Commands {
         visible_alias = "b"
     )]
     Branch {
-        #[arg(help = "Name of the branch to create")]
-        name: String,
+        #[arg(
+            help = "Name of the branch to create, built from type, ticket and description when omitted"
+        )]
+        name: Option<String>,
 
         #[arg(short, long, help = "Base branch to branch from")]
         base: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            overrides_with = "no_publish",
+            help = "Push the new branch and set its upstream"
+        )]
+        publish: bool,
+
+        #[arg(
+            long,
+            overrides_with = "publish",
+            help = "Don't push the new branch even if lgit.publish is set"
+        )]
+        no_publish: bool,
+    },
+
+    #[command(
+        about = "Show where local branches stand compared with their upstream and base",
+        visible_alias = "bs"
+    )]
+    Branches {
+        #[arg(help = "Only show branches whose name contains PATTERN")]
+        pattern: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Base branch to compare with, instead of the one each branch was created from"
+        )]
+        base: Option<String>,
+
+        #[arg(short, long, value_enum, default_value_t = BranchSort::Age, help = "Sort order")]
+        sort: BranchSort,
+
+        #[arg(
+            long,
+            conflicts_with = "no_merged",
+            help = "Only show branches merged into their base"
+        )]
+        merged: bool,
+
+        #[arg(long, help = "Only show branches not merged into their base")]
+        no_merged: bool,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table, help = "Output format")]
+        format: OutputFormat,
     },
 
     #[command(about = "Switch branches", visible_alias = "co")]
@@ -49,12 +105,87 @@ pub enum Commands {
 
         #[arg(short, long, help = "List all branches (local and remote)")]
         all: bool,
+
+        #[arg(
+            long,
+            value_name = "N",
+            conflicts_with = "name",
+            help = "List the N most recently checked out branches (usable as @{-N})"
+        )]
+        recent: Option<usize>,
+
+        #[arg(
+            long,
+            conflicts_with = "autostash",
+            help = "Bring uncommitted changes along to the checked out branch"
+        )]
+        carry: bool,
+
+        #[arg(
+            long,
+            help = "Stash uncommitted changes until you return to the current branch, without asking"
+        )]
+        autostash: bool,
+
+        #[arg(
+            long,
+            value_name = "NUMBER",
+            num_args = 0..=1,
+            conflicts_with = "name",
+            help = "Fetch and checkout pull request NUMBER as pr/NUMBER, or pick one of the fetched ones"
+        )]
+        pr: Option<Option<u32>>,
+
+        #[arg(
+            long,
+            overrides_with = "no_worktree",
+            help = "Open the branch in its own worktree and print its path instead of switching"
+        )]
+        worktree: bool,
+
+        #[arg(
+            long,
+            overrides_with = "worktree",
+            help = "Switch branches in place even if lgit.worktree is set"
+        )]
+        no_worktree: bool,
     },
 
-    #[command(about = "Delete all branches for which remotes are gone. Use with caution!")]
+    #[command(
+        about = "Delete all branches for which remotes are gone. Use with caution!",
+        long_about = "Delete all branches for which remotes are gone. Use with caution!\n\n\
+            With --stale, delete local and remote branches whose last commit is older than AGE instead."
+    )]
     DeleteBranches {
-        #[arg(short, long, help = "Dry run, don't delete anything")]
+        #[arg(
+            short,
+            long,
+            visible_alias = "report",
+            help = "Dry run, don't delete anything"
+        )]
         dry_run: bool,
+
+        #[arg(
+            long,
+            value_name = "AGE",
+            help = "Select local and remote branches whose last commit is older than AGE (e.g. 90d)"
+        )]
+        stale: Option<String>,
+
+        #[arg(
+            long,
+            requires = "stale",
+            help = "Only select stale branches whose last commit author matches"
+        )]
+        author: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            requires = "stale",
+            help = "Base branch to compare stale branches with"
+        )]
+        base: Option<String>,
     },
 
     #[command(about = "Commit as a fixup", visible_alias = "f")]
@@ -63,6 +194,45 @@ pub enum Commands {
         number: u32,
     },
 
+    #[command(
+        about = "Land the current branch on BASE and delete it",
+        long_about = "Land the current branch on BASE and delete it.\n\n\
+            Pending fixup commits are squashed, the branch is rebased onto the freshly fetched BASE, \
+            BASE is fast-forwarded to it (or gets a single squashed commit with --squash) and pushed, \
+            and the branch is deleted locally and on its remote. If any step before the push of BASE \
+            fails, both branches are restored."
+    )]
+    Land {
+        #[arg(
+            short,
+            long,
+            help = "Base branch to land on, defaults to the one the branch was created from"
+        )]
+        base: Option<String>,
+
+        #[arg(short, long, help = "Squash all commits of the branch into one")]
+        squash: bool,
+    },
+
+    #[command(about = "Push the current branch and set its upstream")]
+    Publish {
+        #[arg(
+            short,
+            long,
+            help = "Base branch whose upstream remote is used if lgit.remote isn't set"
+        )]
+        base: Option<String>,
+    },
+
+    #[command(
+        about = "Push the current branch, force-pushing safely after its history was rewritten",
+        long_about = "Push the current branch to its upstream.\n\n\
+            If the branch was rewritten since the last push (e.g. by rebase or autosquash), it is \
+            force-pushed with --force-with-lease and --force-if-includes, so that commits pushed by \
+            others are never overwritten unseen. Protected branches (lgit.protected) are never force-pushed."
+    )]
+    Push,
+
     #[command(
         about = "Rebase current branch on top of latest BASE branch",
         visible_alias = "r"
@@ -70,6 +240,177 @@ pub enum Commands {
     Rebase {
         #[arg(short, long, help = "Base branch to rebase onto")]
         base: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Rebase onto BASE or merge it in, defaults to branch.<name>.lgitStrategy or lgit.strategy"
+        )]
+        strategy: Option<Strategy>,
+
+        #[arg(
+            short = 'x',
+            long,
+            conflicts_with = "all",
+            help = "Command to run after each rebased commit, stopping at the first one it fails for"
+        )]
+        exec: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            conflicts_with = "strategy",
+            help = "Rebase every branch created off BASE with 'lgit branch', skipping merged and protected ones"
+        )]
+        all: bool,
+
+        #[arg(
+            long = "continue",
+            conflicts_with_all = ["base", "strategy", "exec", "all", "abort"],
+            help = "Continue after resolving conflicts and restore stashed changes"
+        )]
+        resume: bool,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["base", "strategy", "exec", "all"],
+            help = "Abort the rebase or merge stopped on conflicts and restore stashed changes"
+        )]
+        abort: bool,
+    },
+
+    #[command(
+        about = "Merge latest BASE branch into the current branch",
+        long_about = "Merge latest BASE branch into the current branch.\n\n\
+            Same as 'lgit rebase --strategy merge', for branches whose history must not be rewritten.",
+        visible_alias = "up"
+    )]
+    Update {
+        #[arg(short, long, help = "Base branch to merge")]
+        base: Option<String>,
+    },
+
+    #[command(
+        about = "Fetch all remotes and fast-forward every local branch behind its upstream",
+        long_about = "Fetch all remotes and fast-forward every local branch behind its upstream.\n\n\
+            Branches are updated without checking them out. Diverged branches and branches whose \
+            upstream is gone are reported, not changed."
+    )]
+    Sync {
+        #[arg(
+            short,
+            long,
+            help = "Rebase the current branch onto the base it was created from afterwards"
+        )]
+        rebase: bool,
+    },
+
+    #[command(
+        about = "Run a command on every commit of the current branch",
+        long_about = "Run a command on every commit of the current branch.\n\n\
+            Each commit since BASE is checked out in a temporary worktree, so the working tree stays \
+            untouched, and reported as passing when the command succeeds there."
+    )]
+    CheckCommits {
+        #[arg(
+            short,
+            long,
+            help = "Base branch to check commits since, defaults to the one the branch was created from"
+        )]
+        base: Option<String>,
+
+        #[arg(
+            short = 'x',
+            long,
+            help = "Command to run on each commit, e.g. \"cargo test\""
+        )]
+        exec: String,
+
+        #[arg(long, help = "Stop at the first failing commit")]
+        fail_fast: bool,
+    },
+
+    #[command(
+        about = "Commit staged changes with a Conventional Commits message",
+        long_about = "Commit staged changes with a Conventional Commits message.\n\n\
+        Without --message, the type, scope, subject, body, breaking change and issues are asked for \
+        one by one, suggesting scopes from the staged paths. The message is checked against the \
+        configured rules before committing either way."
+    )]
+    Commit {
+        #[arg(
+            short,
+            long,
+            help = "Commit with this message after checking it, without prompting"
+        )]
+        message: Option<String>,
+    },
+
+    #[command(
+        about = "Check a commit message against the configured rules",
+        long_about = "Check a commit message against the configured rules.\n\n\
+        Meant for the commit-msg hook, which passes the file holding the message."
+    )]
+    LintMsg {
+        #[arg(help = "File holding the commit message")]
+        file: String,
+    },
+
+    #[command(
+        about = "Check the messages of every commit since BASE, including leftover fixup! commits"
+    )]
+    LintBranch {
+        #[arg(
+            short,
+            long,
+            help = "Base branch (defaults to the one the branch was created from)"
+        )]
+        base: Option<String>,
+
+        #[arg(
+            long,
+            help = "Check the commits of the refs being pushed, read from stdin as git passes them to the pre-push hook"
+        )]
+        pre_push: bool,
+    },
+
+    #[command(
+        about = "Generate a changelog from the Conventional Commits in a range",
+        long_about = "Generate a changelog from the Conventional Commits in a range.\n\n\
+        Commits are grouped by type and scope, issue references are linked and commits \
+        cherry-picked from others in the range are listed once. The range defaults to the \
+        latest tag up to HEAD."
+    )]
+    Changelog {
+        #[arg(help = "Commits to include, e.g. v1.2.0..v1.3.0")]
+        range: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = ChangelogFormat::Markdown, help = "Output format")]
+        format: ChangelogFormat,
+    },
+
+    #[command(
+        about = "Tag the next version with the changelog as its message",
+        long_about = "Tag the next version with the changelog as its message.\n\n\
+        The version is worked out from the latest version tag and the Conventional Commits since: \
+        breaking changes bump the major version, features the minor one and anything else the \
+        patch. The version in lgit.versionFile (e.g. Cargo.toml) is updated and committed first."
+    )]
+    Release {
+        #[arg(value_enum, default_value_t = Bump::Auto, help = "Part of the version to bump")]
+        bump: Bump,
+
+        #[arg(long, help = "Release a numbered pre-release, e.g. rc for 1.3.0-rc.1")]
+        pre: Option<String>,
+
+        #[arg(long, help = "Only show the next version and its changelog")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Install git hooks running lgit's checks")]
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
     },
 
     #[command(
@@ -84,10 +425,172 @@ pub enum Commands {
         number: u32,
     },
 
+    #[command(
+        about = "Pick a stash to apply, pop, drop, rename or branch off, or recover the ones lgit left behind",
+        long_about = "Without an action, pick a stash from a list showing its branch, age and number of files, preview its changes and apply, pop, drop, rename or turn it into a branch. Other actions than 'recover' are passed to git stash."
+    )]
+    Stash {
+        #[command(subcommand)]
+        action: Option<StashAction>,
+    },
+
+    #[command(about = "List, restore and purge branches removed by lgit")]
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    #[command(about = "List, prune and remove worktrees", visible_alias = "wt")]
+    Worktree {
+        #[command(subcommand)]
+        action: WorktreeAction,
+    },
+
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+impl Commands {
+    /// Whether the command changes branches, commits or the working tree, which must not happen
+    /// in the middle of another git operation
+    pub fn mutates(&self) -> bool {
+        match self {
+            Commands::Autosquash { .. }
+            | Commands::Branch { .. }
+            | Commands::Checkout { .. }
+            | Commands::DeleteBranches { .. }
+            | Commands::Fixup { .. }
+            | Commands::Land { .. }
+            | Commands::Publish { .. }
+            | Commands::Push
+            | Commands::Update { .. }
+            | Commands::Sync { .. }
+            | Commands::CherryPick { .. }
+            | Commands::Commit { .. }
+            | Commands::Release { dry_run: false, .. } => true,
+            Commands::Release { dry_run: true, .. } => false,
+            // Resuming is how the operation in progress gets finished
+            Commands::Rebase { resume, abort, .. } => !resume && !abort,
+            // Actions passed to git are left to it, the manager applies and pops stashes itself
+            Commands::Stash { action } => action.is_none(),
+            Commands::Trash { action } => !matches!(action, TrashAction::List),
+            Commands::Worktree { action } => !matches!(action, WorktreeAction::List),
+            Commands::Branches { .. }
+            | Commands::CheckCommits { .. }
+            | Commands::Changelog { .. }
+            | Commands::LintMsg { .. }
+            | Commands::LintBranch { .. }
+            | Commands::Hooks { .. }
+            | Commands::External(_) => false,
+        }
+    }
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum BranchSort {
+    /// Alphabetically
+    Name,
+    /// Most recent commit first
+    Age,
+    /// Most commits ahead of the base first
+    Ahead,
+    /// Most commits behind the base first
+    Behind,
+}
+
+/// How a branch is brought up to date with its base
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum Strategy {
+    /// Rebase the branch onto the base
+    Rebase,
+    /// Merge the base into the branch
+    Merge,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum ChangelogFormat {
+    Markdown,
+    Json,
+}
+
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum Bump {
+    Major,
+    Minor,
+    Patch,
+    /// Worked out from the commits since the latest release
+    Auto,
+}
+
+#[derive(Subcommand)]
+pub enum TrashAction {
+    #[command(about = "List deleted branches")]
+    List,
+
+    #[command(about = "Restore a deleted branch")]
+    Restore {
+        #[arg(
+            help = "Name of the branch to restore, optionally prefixed with its deletion date (DATE/NAME)"
+        )]
+        name: String,
+    },
+
+    #[command(about = "Permanently remove old backups of deleted branches")]
+    Purge {
+        #[arg(
+            long,
+            default_value = "30d",
+            help = "Only purge branches deleted longer ago than this (e.g. 30d, 2w)"
+        )]
+        older_than: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum HooksAction {
+    #[command(
+        about = "Lint commit messages on commit and branches on push, keeping existing hooks"
+    )]
+    Install,
+}
+
+#[derive(Subcommand)]
+pub enum StashAction {
+    #[command(about = "List stashes lgit created but could not restore")]
+    Recover,
+
     #[command(external_subcommand)]
     External(Vec<String>),
 }
 
+#[derive(Subcommand)]
+pub enum WorktreeAction {
+    #[command(about = "List worktrees and the branches checked out in them")]
+    List,
+
+    #[command(about = "Clean up worktrees whose directory was deleted")]
+    Prune,
+
+    #[command(about = "Remove a worktree, picked interactively unless given")]
+    Remove {
+        #[arg(help = "Path of the worktree or name of the branch checked out in it")]
+        name: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Remove the worktree even if it has uncommitted changes"
+        )]
+        force: bool,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -95,15 +598,15 @@ mod tests {
 
     #[test]
     fn test_args_parse_verbose_flag() {
-        let args = Args::try_parse_from(&["lgit", "--verbose", "branch", "test-branch"]);
+        let args = Args::try_parse_from(["lgit", "--verbose", "branch", "test-branch"]);
 
         assert!(args.is_ok());
         let args = args.unwrap();
         assert!(args.verbose);
 
         match args.command {
-            Some(Commands::Branch { name, base: _ }) => {
-                assert_eq!(name, "test-branch");
+            Some(Commands::Branch { name, .. }) => {
+                assert_eq!(name, Some("test-branch".to_string()));
             }
             _ => panic!("Expected Branch command"),
         }
@@ -111,14 +614,14 @@ mod tests {
 
     #[test]
     fn test_args_parse_short_verbose_flag() {
-        let args = Args::try_parse_from(&["lgit", "-v", "autosquash", "--number", "5"]);
+        let args = Args::try_parse_from(["lgit", "-v", "autosquash", "--number", "5"]);
 
         assert!(args.is_ok());
         let args = args.unwrap();
         assert!(args.verbose);
 
         match args.command {
-            Some(Commands::Autosquash { number, base: _ }) => {
+            Some(Commands::Autosquash { number, .. }) => {
                 assert_eq!(number, Some(5));
             }
             _ => panic!("Expected Autosquash command"),
@@ -127,7 +630,7 @@ mod tests {
 
     #[test]
     fn test_args_parse_no_verbose() {
-        let args = Args::try_parse_from(&["lgit", "branch", "test-branch"]);
+        let args = Args::try_parse_from(["lgit", "branch", "test-branch"]);
 
         assert!(args.is_ok());
         let args = args.unwrap();
@@ -136,12 +639,12 @@ mod tests {
 
     #[test]
     fn test_branch_command_with_base() {
-        let args = Args::try_parse_from(&["lgit", "branch", "feature-branch", "--base", "develop"]);
+        let args = Args::try_parse_from(["lgit", "branch", "feature-branch", "--base", "develop"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Branch { name, base }) => {
-                assert_eq!(name, "feature-branch");
+            Some(Commands::Branch { name, base, .. }) => {
+                assert_eq!(name, Some("feature-branch".to_string()));
                 assert_eq!(base, Some("develop".to_string()));
             }
             _ => panic!("Expected Branch command"),
@@ -150,12 +653,12 @@ mod tests {
 
     #[test]
     fn test_branch_alias() {
-        let args = Args::try_parse_from(&["lgit", "b", "feature-branch"]);
+        let args = Args::try_parse_from(["lgit", "b", "feature-branch"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Branch { name, base: _ }) => {
-                assert_eq!(name, "feature-branch");
+            Some(Commands::Branch { name, .. }) => {
+                assert_eq!(name, Some("feature-branch".to_string()));
             }
             _ => panic!("Expected Branch command"),
         }
@@ -163,30 +666,96 @@ mod tests {
 
     #[test]
     fn test_checkout_command_with_flags() {
-        let args = Args::try_parse_from(&["lgit", "checkout", "--remote", "--all"]);
+        let args = Args::try_parse_from(["lgit", "checkout", "--remote", "--all"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Checkout { name, remote, all }) => {
+            Some(Commands::Checkout {
+                name,
+                remote,
+                all,
+                recent,
+                carry,
+                autostash,
+                pr,
+                worktree,
+                no_worktree,
+            }) => {
                 assert_eq!(name, None);
                 assert!(remote);
                 assert!(all);
+                assert_eq!(recent, None);
+                assert!(!carry);
+                assert!(!autostash);
+                assert_eq!(pr, None);
+                assert!(!worktree);
+                assert!(!no_worktree);
+            }
+            _ => panic!("Expected Checkout command"),
+        }
+    }
+
+    #[test]
+    fn test_checkout_recent() {
+        let args = Args::try_parse_from(["lgit", "co", "--recent", "5"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Checkout { recent, .. }) => {
+                assert_eq!(recent, Some(5));
             }
             _ => panic!("Expected Checkout command"),
         }
     }
 
+    #[test]
+    fn test_checkout_pull_request() {
+        let args = Args::try_parse_from(["lgit", "co", "--pr", "42"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Checkout { name, pr, .. }) => {
+                assert_eq!(name, None);
+                assert_eq!(pr, Some(Some(42)));
+            }
+            _ => panic!("Expected Checkout command"),
+        }
+    }
+
+    #[test]
+    fn test_checkout_pull_request_picker() {
+        let args = Args::try_parse_from(["lgit", "co", "--pr"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Checkout { pr, .. }) => {
+                assert_eq!(pr, Some(None));
+            }
+            _ => panic!("Expected Checkout command"),
+        }
+    }
+
+    #[test]
+    fn test_checkout_carry_conflicts_with_autostash() {
+        let args = Args::try_parse_from(["lgit", "co", "main", "--carry", "--autostash"]);
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_checkout_recent_conflicts_with_name() {
+        let args = Args::try_parse_from(["lgit", "co", "main", "--recent", "5"]);
+
+        assert!(args.is_err());
+    }
+
     #[test]
     fn test_checkout_alias() {
-        let args = Args::try_parse_from(&["lgit", "co", "main"]);
+        let args = Args::try_parse_from(["lgit", "co", "main"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Checkout {
-                name,
-                remote: _,
-                all: _,
-            }) => {
+            Some(Commands::Checkout { name, .. }) => {
                 assert_eq!(name, Some("main".to_string()));
             }
             _ => panic!("Expected Checkout command"),
@@ -195,20 +764,57 @@ mod tests {
 
     #[test]
     fn test_delete_branches_dry_run() {
-        let args = Args::try_parse_from(&["lgit", "delete-branches", "--dry-run"]);
+        let args = Args::try_parse_from(["lgit", "delete-branches", "--dry-run"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::DeleteBranches { dry_run }) => {
+            Some(Commands::DeleteBranches { dry_run, stale, .. }) => {
                 assert!(dry_run);
+                assert_eq!(stale, None);
             }
             _ => panic!("Expected DeleteBranches command"),
         }
     }
 
+    #[test]
+    fn test_delete_branches_stale_report() {
+        let args = Args::try_parse_from([
+            "lgit",
+            "delete-branches",
+            "--stale",
+            "90d",
+            "--author",
+            "jane",
+            "--report",
+        ]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::DeleteBranches {
+                dry_run,
+                stale,
+                author,
+                base,
+            }) => {
+                assert!(dry_run);
+                assert_eq!(stale, Some("90d".to_string()));
+                assert_eq!(author, Some("jane".to_string()));
+                assert_eq!(base, None);
+            }
+            _ => panic!("Expected DeleteBranches command"),
+        }
+    }
+
+    #[test]
+    fn test_delete_branches_author_requires_stale() {
+        let args = Args::try_parse_from(["lgit", "delete-branches", "--author", "jane"]);
+
+        assert!(args.is_err());
+    }
+
     #[test]
     fn test_fixup_with_number() {
-        let args = Args::try_parse_from(&["lgit", "fixup", "--number", "10"]);
+        let args = Args::try_parse_from(["lgit", "fixup", "--number", "10"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
@@ -221,7 +827,7 @@ mod tests {
 
     #[test]
     fn test_fixup_default_number() {
-        let args = Args::try_parse_from(&["lgit", "fixup"]);
+        let args = Args::try_parse_from(["lgit", "fixup"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
@@ -234,11 +840,11 @@ mod tests {
 
     #[test]
     fn test_autosquash_with_number() {
-        let args = Args::try_parse_from(&["lgit", "autosquash", "--number", "3"]);
+        let args = Args::try_parse_from(["lgit", "autosquash", "--number", "3"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Autosquash { number, base: _ }) => {
+            Some(Commands::Autosquash { number, .. }) => {
                 assert_eq!(number, Some(3));
             }
             _ => panic!("Expected Autosquash command"),
@@ -247,11 +853,11 @@ mod tests {
 
     #[test]
     fn test_autosquash_alias() {
-        let args = Args::try_parse_from(&["lgit", "as"]);
+        let args = Args::try_parse_from(["lgit", "as"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Autosquash { number: _, base: _ }) => {
+            Some(Commands::Autosquash { .. }) => {
                 // Success - autosquash alias works
             }
             _ => panic!("Expected Autosquash command"),
@@ -260,11 +866,11 @@ mod tests {
 
     #[test]
     fn test_rebase_with_base() {
-        let args = Args::try_parse_from(&["lgit", "rebase", "--base", "main"]);
+        let args = Args::try_parse_from(["lgit", "rebase", "--base", "main"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
-            Some(Commands::Rebase { base }) => {
+            Some(Commands::Rebase { base, .. }) => {
                 assert_eq!(base, Some("main".to_string()));
             }
             _ => panic!("Expected Rebase command"),
@@ -273,8 +879,7 @@ mod tests {
 
     #[test]
     fn test_cherry_pick_command() {
-        let args =
-            Args::try_parse_from(&["lgit", "cherry-pick", "feature-branch", "--number", "5"]);
+        let args = Args::try_parse_from(["lgit", "cherry-pick", "feature-branch", "--number", "5"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
@@ -288,7 +893,7 @@ mod tests {
 
     #[test]
     fn test_cherry_pick_alias() {
-        let args = Args::try_parse_from(&["lgit", "cp", "dev"]);
+        let args = Args::try_parse_from(["lgit", "cp", "dev"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
@@ -302,7 +907,7 @@ mod tests {
 
     #[test]
     fn test_external_command() {
-        let args = Args::try_parse_from(&["lgit", "status", "--short"]);
+        let args = Args::try_parse_from(["lgit", "status", "--short"]);
 
         assert!(args.is_ok());
         match args.unwrap().command {
@@ -315,10 +920,404 @@ mod tests {
 
     #[test]
     fn test_no_command() {
-        let args = Args::try_parse_from(&["lgit"]);
+        let args = Args::try_parse_from(["lgit"]);
 
         assert!(args.is_ok());
         let args = args.unwrap();
         assert!(args.command.is_none());
     }
+
+    #[test]
+    fn test_trash_restore() {
+        let args = Args::try_parse_from(["lgit", "trash", "restore", "feature-branch"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Trash {
+                action: TrashAction::Restore { name },
+            }) => {
+                assert_eq!(name, "feature-branch");
+            }
+            _ => panic!("Expected Trash Restore command"),
+        }
+    }
+
+    #[test]
+    fn test_trash_purge_default_age() {
+        let args = Args::try_parse_from(["lgit", "trash", "purge"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Trash {
+                action: TrashAction::Purge { older_than },
+            }) => {
+                assert_eq!(older_than, "30d");
+            }
+            _ => panic!("Expected Trash Purge command"),
+        }
+    }
+
+    #[test]
+    fn test_checkout_no_worktree_overrides_worktree() {
+        let args = Args::try_parse_from(["lgit", "co", "main", "--worktree", "--no-worktree"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Checkout {
+                worktree,
+                no_worktree,
+                ..
+            }) => {
+                assert!(!worktree);
+                assert!(no_worktree);
+            }
+            _ => panic!("Expected Checkout command"),
+        }
+    }
+
+    #[test]
+    fn test_worktree_remove_force() {
+        let args = Args::try_parse_from(["lgit", "wt", "remove", "feature", "--force"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Worktree {
+                action: WorktreeAction::Remove { name, force },
+            }) => {
+                assert_eq!(name, Some("feature".to_string()));
+                assert!(force);
+            }
+            _ => panic!("Expected Worktree Remove command"),
+        }
+    }
+
+    #[test]
+    fn test_commit_with_message() {
+        let args = Args::try_parse_from(["lgit", "commit", "-m", "fix: handle empty input"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Commit { message }) => {
+                assert_eq!(message, Some("fix: handle empty input".to_string()))
+            }
+            _ => panic!("Expected Commit command"),
+        }
+    }
+
+    #[test]
+    fn test_lint_msg() {
+        let args = Args::try_parse_from(["lgit", "lint-msg", ".git/COMMIT_EDITMSG"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::LintMsg { file }) => assert_eq!(file, ".git/COMMIT_EDITMSG"),
+            _ => panic!("Expected LintMsg command"),
+        }
+    }
+
+    #[test]
+    fn test_changelog() {
+        let args =
+            Args::try_parse_from(["lgit", "changelog", "v1.0.0..v1.1.0", "--format", "json"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Changelog { range, format }) => {
+                assert_eq!(range, Some("v1.0.0..v1.1.0".to_string()));
+                assert!(matches!(format, ChangelogFormat::Json));
+            }
+            _ => panic!("Expected Changelog command"),
+        }
+    }
+
+    #[test]
+    fn test_release() {
+        let args = Args::try_parse_from(["lgit", "release", "minor", "--pre", "rc"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Release { bump, pre, dry_run }) => {
+                assert_eq!(bump, Bump::Minor);
+                assert_eq!(pre, Some("rc".to_string()));
+                assert!(!dry_run);
+            }
+            _ => panic!("Expected Release command"),
+        }
+    }
+
+    #[test]
+    fn test_release_defaults_to_auto() {
+        let args = Args::try_parse_from(["lgit", "release", "--dry-run"]);
+
+        assert!(args.is_ok());
+        assert!(matches!(
+            args.unwrap().command,
+            Some(Commands::Release {
+                bump: Bump::Auto,
+                dry_run: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_hooks_install() {
+        let args = Args::try_parse_from(["lgit", "hooks", "install"]);
+
+        assert!(args.is_ok());
+        assert!(matches!(
+            args.unwrap().command,
+            Some(Commands::Hooks {
+                action: HooksAction::Install
+            })
+        ));
+    }
+
+    #[test]
+    fn test_stash_recover() {
+        let args = Args::try_parse_from(["lgit", "stash", "recover"]);
+
+        assert!(args.is_ok());
+        assert!(matches!(
+            args.unwrap().command,
+            Some(Commands::Stash {
+                action: Some(StashAction::Recover)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_stash_passes_other_actions_to_git() {
+        let args = Args::try_parse_from(["lgit", "stash", "show", "-p", "stash@{1}"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Stash {
+                action: Some(StashAction::External(args)),
+            }) => assert_eq!(args, ["show", "-p", "stash@{1}"]),
+            _ => panic!("Expected Stash External command"),
+        }
+    }
+
+    #[test]
+    fn test_branch_without_name() {
+        let args = Args::try_parse_from(["lgit", "b"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Branch { name, .. }) => {
+                assert_eq!(name, None);
+            }
+            _ => panic!("Expected Branch command"),
+        }
+    }
+
+    #[test]
+    fn test_branch_publish() {
+        let args = Args::try_parse_from(["lgit", "b", "feature", "-p"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Branch {
+                publish,
+                no_publish,
+                ..
+            }) => {
+                assert!(publish);
+                assert!(!no_publish);
+            }
+            _ => panic!("Expected Branch command"),
+        }
+    }
+
+    #[test]
+    fn test_publish_with_base() {
+        let args = Args::try_parse_from(["lgit", "publish", "--base", "develop"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Publish { base }) => {
+                assert_eq!(base, Some("develop".to_string()));
+            }
+            _ => panic!("Expected Publish command"),
+        }
+    }
+
+    #[test]
+    fn test_push_command() {
+        let args = Args::try_parse_from(["lgit", "push"]);
+
+        assert!(matches!(args.unwrap().command, Some(Commands::Push)));
+    }
+
+    #[test]
+    fn test_sync_rebase() {
+        let args = Args::try_parse_from(["lgit", "sync", "--rebase"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Sync { rebase }) => {
+                assert!(rebase);
+            }
+            _ => panic!("Expected Sync command"),
+        }
+    }
+
+    #[test]
+    fn test_branches_options() {
+        let args = Args::try_parse_from([
+            "lgit",
+            "branches",
+            "feat",
+            "--sort",
+            "behind",
+            "--no-merged",
+            "--format",
+            "json",
+        ]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Branches {
+                pattern,
+                base,
+                sort,
+                merged,
+                no_merged,
+                format,
+            }) => {
+                assert_eq!(pattern, Some("feat".to_string()));
+                assert_eq!(base, None);
+                assert!(matches!(sort, BranchSort::Behind));
+                assert!(!merged);
+                assert!(no_merged);
+                assert!(matches!(format, OutputFormat::Json));
+            }
+            _ => panic!("Expected Branches command"),
+        }
+    }
+
+    #[test]
+    fn test_branches_merged_conflicts_with_no_merged() {
+        let args = Args::try_parse_from(["lgit", "branches", "--merged", "--no-merged"]);
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_land_squash() {
+        let args = Args::try_parse_from(["lgit", "land", "--squash", "-b", "develop"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Land { base, squash }) => {
+                assert_eq!(base, Some("develop".to_string()));
+                assert!(squash);
+            }
+            _ => panic!("Expected Land command"),
+        }
+    }
+
+    #[test]
+    fn test_rebase_strategy() {
+        let args = Args::try_parse_from(["lgit", "rebase", "--strategy", "merge"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Rebase {
+                base,
+                strategy,
+                exec,
+                all,
+                resume,
+                abort,
+            }) => {
+                assert_eq!(base, None);
+                assert_eq!(strategy, Some(Strategy::Merge));
+                assert_eq!(exec, None);
+                assert!(!all && !resume && !abort);
+            }
+            _ => panic!("Expected Rebase command"),
+        }
+    }
+
+    #[test]
+    fn test_rebase_continue_conflicts_with_base() {
+        let args = Args::try_parse_from(["lgit", "rebase", "--continue", "--base", "main"]);
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_update_alias() {
+        let args = Args::try_parse_from(["lgit", "up", "-b", "develop"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Update { base }) => assert_eq!(base, Some("develop".to_string())),
+            _ => panic!("Expected Update command"),
+        }
+    }
+
+    #[test]
+    fn test_rebase_all() {
+        let args = Args::try_parse_from(["lgit", "rebase", "--all", "-b", "develop"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::Rebase { base, all, .. }) => {
+                assert_eq!(base, Some("develop".to_string()));
+                assert!(all);
+            }
+            _ => panic!("Expected Rebase command"),
+        }
+
+        let args = Args::try_parse_from(["lgit", "rebase", "--all", "--strategy", "merge"]);
+
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_check_commits() {
+        let args =
+            Args::try_parse_from(["lgit", "check-commits", "-x", "cargo test", "--fail-fast"]);
+
+        assert!(args.is_ok());
+        match args.unwrap().command {
+            Some(Commands::CheckCommits {
+                base,
+                exec,
+                fail_fast,
+            }) => {
+                assert_eq!(base, None);
+                assert_eq!(exec, "cargo test");
+                assert!(fail_fast);
+            }
+            _ => panic!("Expected CheckCommits command"),
+        }
+
+        assert!(Args::try_parse_from(["lgit", "check-commits"]).is_err());
+    }
+
+    #[test]
+    fn test_mutates() {
+        let mutates = |args: &[&str]| {
+            Args::try_parse_from(args)
+                .unwrap()
+                .command
+                .is_some_and(|command| command.mutates())
+        };
+
+        assert!(mutates(&["lgit", "rebase"]));
+        assert!(mutates(&["lgit", "trash", "restore", "feature"]));
+        assert!(!mutates(&["lgit", "rebase", "--continue"]));
+        assert!(!mutates(&["lgit", "branches"]));
+        assert!(!mutates(&["lgit", "worktree", "list"]));
+        assert!(!mutates(&["lgit", "status"]));
+        assert!(mutates(&["lgit", "stash"]));
+        assert!(!mutates(&["lgit", "stash", "recover"]));
+        assert!(!mutates(&["lgit", "lint-msg", ".git/COMMIT_EDITMSG"]));
+        assert!(mutates(&["lgit", "release"]));
+        assert!(!mutates(&["lgit", "release", "--dry-run"]));
+    }
 }
@@ -4,12 +4,26 @@ use mockall::mock;
 
 pub mod autosquash;
 pub mod branch;
+pub mod branches;
+pub mod changelog;
+pub mod check_commits;
 pub mod checkout;
 pub mod cherry_pick;
+pub mod commit;
 pub mod delete_branches;
 pub mod fixup;
 pub mod git_fallback;
+pub mod hooks;
+pub mod land;
+pub mod lint;
+pub mod publish;
+pub mod push;
 pub mod rebase;
+pub mod release;
+pub mod stash;
+pub mod sync;
+pub mod trash;
+pub mod worktree;
 
 pub trait Exec {
     fn exec(&self, args: &[&str], verbose: bool, inherit_stdio: bool) -> Result<String, ()>;
@@ -32,7 +46,9 @@ impl Exec for Cmd {
                     .stdout(Stdio::inherit())
                     .stderr(Stdio::inherit())
                     .status()
-                    .expect(&format!("Failed to execute git command: git {}", args.join(" ")));
+                    .unwrap_or_else(|_| {
+                        panic!("Failed to execute git command: git {}", args.join(" "))
+                    });
 
                 if status.success() {
                     Ok(String::new())
@@ -41,10 +57,14 @@ impl Exec for Cmd {
                 }
             }
             false => {
+                // Captured output is parsed, so keep it independent of the user's locale
                 let output = process::Command::new(cmd)
                     .args(args)
+                    .env("LC_ALL", "C")
                     .output()
-                    .expect(&format!("Failed to execute git command: git {}", args.join(" ")));
+                    .unwrap_or_else(|_| {
+                        panic!("Failed to execute git command: git {}", args.join(" "))
+                    });
 
                 let status = output.status;
 
@@ -121,8 +141,8 @@ mod tests {
         let result = cmd.exec(&["version"], false, true);
 
         // With inherit_stdio=true, successful commands return empty string
-        if result.is_ok() {
-            assert_eq!(result.unwrap(), "");
+        if let Ok(output) = result {
+            assert_eq!(output, "");
         }
     }
 
@@ -4,6 +4,7 @@ pub fn run<T: Exec>(
     cmd: &T,
     base: &str,
     number: Option<u32>,
+    exec: Option<&str>,
     verbose: bool,
 ) -> Result<(), Option<String>> {
     let mut args = vec![
@@ -15,6 +16,10 @@ pub fn run<T: Exec>(
     ];
     let arg: String;
 
+    if let Some(exec) = exec {
+        args.extend(["--exec", exec]);
+    }
+
     match number {
         Some(number) => {
             arg = format!("HEAD~{number}");
@@ -23,9 +28,16 @@ pub fn run<T: Exec>(
         None => args.push(base),
     }
 
-    cmd.exec(&args, verbose, false)
+    // The output of the command shows why a commit failed it
+    cmd.exec(&args, verbose, exec.is_some())
         .map(|_| ())
-        .map_err(|()| Some("Failed to auto squash commits".to_string()))
+        .map_err(|()| match exec {
+            Some(exec) => Some(format!(
+                "Failed to auto squash commits, if a commit failed '{}' fix it and run 'lgit rebase --continue'",
+                exec
+            )),
+            None => Some("Failed to auto squash commits".to_string()),
+        })
 }
 
 #[cfg(test)]
@@ -52,7 +64,7 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        let result = run(&cmd, "HEAD~1", None, false);
+        let result = run(&cmd, "HEAD~1", None, None, false);
 
         assert!(result.is_ok());
     }
@@ -75,7 +87,32 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        let result = run(&cmd, "main", None, false);
+        let result = run(&cmd, "main", None, None, false);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_with_exec_supplied() {
+        let mut cmd = MockCmd::new();
+        cmd.expect_exec()
+            .withf(|args, verbose, inherit_stdio| {
+                args == [
+                    "-c",
+                    "sequence.editor=:",
+                    "rebase",
+                    "--interactive",
+                    "--autosquash",
+                    "--exec",
+                    "make test",
+                    "HEAD~2",
+                ] && !(*verbose)
+                    && *inherit_stdio
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = run(&cmd, "main", Some(2), Some("make test"), false);
 
         assert!(result.is_ok());
     }
@@ -1,66 +1,452 @@
-use crate::commands::Exec;
-use crate::utils::{refresh_base, stash, unstash};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{FuzzySelect, Input};
+use regex::Regex;
+
+use crate::commands::{publish, Exec};
+use crate::config;
+use crate::parser;
+use crate::utils::{refresh_base, set_recorded_base, stash, unstash};
+
+/// Template interactive branch names are built from unless `lgit.branchTemplate` says otherwise
+const DEFAULT_TEMPLATE: &str = "{type}/{ticket}-{description}";
+
+/// Types offered by the interactive mode unless `lgit.branchTypes` says otherwise
+const DEFAULT_TYPES: &str = "feat,fix,chore,docs,refactor,test";
 
 pub fn run<T: Exec>(
     command: &T,
-    name: &str,
+    name: Option<String>,
     base: &str,
+    publish: bool,
     verbose: bool,
 ) -> Result<(), Option<String>> {
-    let unsaved_changes = stash(command, verbose)?;
+    let name = match name {
+        Some(name) => name,
+        None => prompt_name(command, verbose)?,
+    };
 
-    refresh_base(command, base, verbose)
+    validate(command, &name, verbose)?;
+
+    let start = refresh_base(command, base, verbose)
         .map_err(|()| format!("Failed to refresh base branch '{}'", base))?;
 
-    command
-        .exec(&["checkout", "-b", name], verbose, false)
-        .map_err(|()| format!("Failed to create branch '{}'", name))?;
+    // Not tracking the base, so that pushing the new branch doesn't default to the base
+    let args = ["checkout", "--no-track", "-b", &name, &start];
 
-    if unsaved_changes {
-        unstash(command, verbose)?;
+    // Uncommitted changes come along unless they conflict with the base, only then stash them
+    if command.exec(&args, verbose, false).is_err() {
+        let Some(stashed) = stash(command, "branch", verbose)? else {
+            return Err(Some(format!(
+                "Failed to create branch '{}' from '{}'",
+                name, start
+            )));
+        };
+
+        let result = command
+            .exec(&args, verbose, false)
+            .map_err(|()| format!("Failed to create branch '{}' from '{}'", name, start));
+
+        unstash(command, &stashed, verbose)?;
+
+        result?;
     }
 
+    set_recorded_base(command, &name, base, verbose)
+        .map_err(|()| format!("Failed to record base of branch '{}'", name))?;
+
     println!("Created branch {name}");
 
+    if publish {
+        publish::publish(command, &name, base, verbose)?;
+    }
+
+    Ok(())
+}
+
+/// Rejects names git refuses, names not matching `lgit.branchPattern` and names that clash with an
+/// existing branch, including ones only differing in case on case-insensitive file systems
+fn validate<T: Exec>(command: &T, name: &str, verbose: bool) -> Result<(), String> {
+    command
+        .exec(&["check-ref-format", "--branch", name], verbose, false)
+        .map_err(|()| format!("'{}' is not a valid branch name", name))?;
+
+    if let Some(pattern) = config::get(command, "lgit.branchPattern", verbose) {
+        let regex = Regex::new(&pattern)
+            .map_err(|err| format!("Invalid lgit.branchPattern '{}': {}", pattern, err))?;
+
+        if !regex.is_match(name) {
+            return Err(format!(
+                "Branch name '{}' doesn't match lgit.branchPattern '{}'",
+                name, pattern
+            ));
+        }
+    }
+
+    let branches = parser::branches(command, &["refs/heads"], verbose)
+        .map_err(|()| "Failed to list local branches".to_string())?;
+
+    let name_lower = name.to_lowercase();
+
+    // `a` and `a/b` can't coexist either, as refs are stored as files and directories
+    if let Some(existing) = branches.iter().find(|branch| {
+        let existing = branch.name().to_lowercase();
+
+        existing == name_lower
+            || name_lower.starts_with(&format!("{existing}/"))
+            || existing.starts_with(&format!("{name_lower}/"))
+    }) {
+        return Err(format!(
+            "Branch '{}' collides with existing branch '{}'",
+            name,
+            existing.name()
+        ));
+    }
+
     Ok(())
 }
 
+/// Asks for the type, ticket and description of the change and builds the branch name from them
+fn prompt_name<T: Exec>(command: &T, verbose: bool) -> Result<String, String> {
+    let types: Vec<String> = config::get(command, "lgit.branchTypes", verbose)
+        .unwrap_or_else(|| DEFAULT_TYPES.to_string())
+        .split(',')
+        .map(|kind| kind.trim().to_string())
+        .filter(|kind| !kind.is_empty())
+        .collect();
+
+    let theme = ColorfulTheme::default();
+
+    let kind = FuzzySelect::with_theme(&theme)
+        .with_prompt("Type of change")
+        .default(0)
+        .items(&types)
+        .interact()
+        .map_err(|err| format!("Failed to select type: {}", err))?;
+
+    let ticket: String = Input::with_theme(&theme)
+        .with_prompt("Ticket (leave empty for none)")
+        .allow_empty(true)
+        .interact_text()
+        .map_err(|err| format!("Failed to read ticket: {}", err))?;
+
+    let description: String = Input::with_theme(&theme)
+        .with_prompt("Short description")
+        .interact_text()
+        .map_err(|err| format!("Failed to read description: {}", err))?;
+
+    let template = config::get(command, "lgit.branchTemplate", verbose)
+        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+
+    Ok(render_template(
+        &template,
+        &types[kind],
+        &ticket,
+        &description,
+    ))
+}
+
+/// Fills the `{type}`, `{ticket}` and `{description}` placeholders of `template`, dropping the
+/// separators left over by empty values
+fn render_template(template: &str, kind: &str, ticket: &str, description: &str) -> String {
+    let name = template
+        .replace("{type}", &slugify(kind))
+        .replace("{ticket}", &ticket.trim().replace(char::is_whitespace, "-"))
+        .replace("{description}", &slugify(description));
+
+    name.split('/')
+        .map(|part| part.trim_matches(|c| c == '-' || c == '_'))
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut part = part.to_string();
+
+            while part.contains("--") {
+                part = part.replace("--", "-");
+            }
+
+            part
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Lowercases `text` and replaces every run of characters other than ASCII letters and digits
+/// with a single `-`
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::commands::branch::run;
+    use super::*;
     use crate::commands::MockCmd;
 
-    #[test]
-    fn test_run_with_master_branch() {
+    fn cmd_validate(pattern: Option<&'static str>, branches: &'static str) -> MockCmd {
         let mut command = MockCmd::new();
         command
             .expect_exec()
             .times(1)
             .withf(|args, verbose, inherit_stderr| {
-                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
+                args.starts_with(&["check-ref-format", "--branch"])
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["config", "--get", "lgit.branchPattern"])
+            .returning(move |_, _, _| pattern.map(str::to_string).ok_or(()));
+        command
+            .expect_exec()
+            .times(0..=1)
+            .withf(|args, _, _| args[0] == "for-each-ref" && args[2] == "refs/heads")
+            .returning(move |_, _, _| Ok(branches.to_string()));
+
+        command
+    }
+
+    fn expect_refresh_base(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.fastForwardBase"])
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .returning(|_, _, _| Err(()));
         command
             .expect_exec()
             .times(1)
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
+                args == ["fetch", "origin", "main:main"] && !(*verbose) && !(*inherit_stderr)
             })
             .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| {
+                args == [
+                    "show-ref",
+                    "--verify",
+                    "--quiet",
+                    "refs/remotes/origin/main",
+                ]
+            })
+            .returning(|_, _, _| Ok(String::new()));
+    }
+
+    fn expect_record_base(command: &mut MockCmd) {
         command
             .expect_exec()
             .times(1)
             .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
+                args == ["config", "branch.test.lgitBase", "main"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .returning(|_, _, _| Ok(String::new()));
+    }
+
+    #[test]
+    fn test_run_with_master_branch() {
+        let mut command = cmd_validate(None, "*\0refs/heads/main\0\0\0\0\0\n");
+        expect_refresh_base(&mut command);
+        expect_record_base(&mut command);
         command
             .expect_exec()
             .times(1)
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "-b", "test"] && !(*verbose) && !(*inherit_stderr)
+                args == ["checkout", "--no-track", "-b", "test", "origin/main"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .returning(|_, _, _| Ok(String::new()));
+        assert_eq!(
+            run(&command, Some("test".to_string()), "main", false, false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_run_publishes_new_branch() {
+        let mut command = cmd_validate(None, "*\0refs/heads/main\0\0\0\0\0\n");
+        expect_refresh_base(&mut command);
+        expect_record_base(&mut command);
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["checkout", "--no-track", "-b", "test", "origin/main"])
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["config", "--get", "branch.main.remote"])
+            .returning(|_, _, _| Ok("origin\n".to_string()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, inherit_stdio| {
+                args == ["push", "--set-upstream", "origin", "test"] && *inherit_stdio
             })
             .returning(|_, _, _| Ok(String::new()));
-        assert_eq!(run(&command, "test", "main", false), Ok(()));
+
+        assert_eq!(
+            run(&command, Some("test".to_string()), "main", true, false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_run_stashes_conflicting_changes() {
+        let mut command = cmd_validate(None, "*\0refs/heads/main\0\0\0\0\0\n");
+        expect_refresh_base(&mut command);
+        expect_record_base(&mut command);
+        let mut attempts = 0;
+        command
+            .expect_exec()
+            .times(2)
+            .withf(|args, _, _| args == ["checkout", "--no-track", "-b", "test", "origin/main"])
+            .returning(move |_, _, _| {
+                attempts += 1;
+
+                if attempts == 1 {
+                    Err(())
+                } else {
+                    Ok(String::new())
+                }
+            });
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["status", "--porcelain"])
+            .returning(|_, _, _| Ok("M file.txt".to_string()));
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args.starts_with(&["stash", "push", "--include-untracked"]))
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "--quiet", "refs/stash"])
+            .times(2)
+            .returning({
+                // No stash before pushing one, the new stash after
+                let mut calls = 0;
+                move |_, _, _| {
+                    calls += 1;
+                    (calls > 1).then(|| "abc123\n".to_string()).ok_or(())
+                }
+            });
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args.starts_with(&["stash", "list", "-z"]))
+            .returning(|_, _, _| {
+                Ok(
+                    "stash@{0}\0abc123\x001760882700\0On main: lgit-stash:branch 2026-10-19 14:05 UTC\0"
+                        .to_string(),
+                )
+            });
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{0}"])
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            run(&command, Some("test".to_string()), "main", false, false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid_ref_format() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .times(1)
+            .withf(|args, _, _| args == ["check-ref-format", "--branch", "bad..name"])
+            .returning(|_, _, _| Err(()));
+
+        assert_eq!(
+            validate(&command, "bad..name", false),
+            Err("'bad..name' is not a valid branch name".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_pattern() {
+        let command = cmd_validate(Some("^(feat|fix)/[A-Z]+-[0-9]+-[a-z0-9-]+$\n"), "");
+
+        assert!(validate(&command, "feature/login", false).is_err());
+    }
+
+    #[test]
+    fn test_validate_pattern_match() {
+        let command = cmd_validate(
+            Some("^(feat|fix)/[A-Z]+-[0-9]+-[a-z0-9-]+$\n"),
+            " \0refs/heads/main\0\0\0\0\0\n",
+        );
+
+        assert_eq!(validate(&command, "feat/ABC-123-login-form", false), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_case_insensitive_collision() {
+        let command = cmd_validate(None, " \0refs/heads/Feature/Login\0\0\0\0\0\n");
+
+        assert_eq!(
+            validate(&command, "feature/login", false),
+            Err("Branch 'feature/login' collides with existing branch 'Feature/Login'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_directory_collision() {
+        let command = cmd_validate(None, " \0refs/heads/feat\0\0\0\0\0\n");
+
+        assert_eq!(
+            validate(&command, "feat/login", false),
+            Err("Branch 'feat/login' collides with existing branch 'feat'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, "feat", "ABC-123", "Add login form!"),
+            "feat/ABC-123-add-login-form"
+        );
+    }
+
+    #[test]
+    fn test_render_template_without_ticket() {
+        assert_eq!(
+            render_template(DEFAULT_TEMPLATE, "fix", " ", "Crash on start"),
+            "fix/crash-on-start"
+        );
+        assert_eq!(
+            render_template("{ticket}/{type}-{description}", "chore", "", "Bump deps"),
+            "chore-bump-deps"
+        );
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(
+            slugify("  Speed up   fast_Parser v2! "),
+            "speed-up-fast-parser-v2"
+        );
     }
 }
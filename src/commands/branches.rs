@@ -0,0 +1,408 @@
+use serde::Serialize;
+
+use crate::cli::{BranchSort, OutputFormat};
+use crate::commands::Exec;
+use crate::parser;
+use crate::utils::{ahead_behind, format_age, get_recorded_bases, now};
+
+pub struct Options {
+    pub base: String,
+    /// Compare branches with the base recorded by `lgit branch` rather than `base` when they have one
+    pub recorded_bases: bool,
+    pub pattern: Option<String>,
+    pub merged: bool,
+    pub no_merged: bool,
+    pub sort: BranchSort,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Row {
+    name: String,
+    head: bool,
+    upstream: Option<String>,
+    upstream_gone: bool,
+    upstream_ahead: u32,
+    upstream_behind: u32,
+    base: String,
+    /// The base no longer exists, e.g. a recorded base that was deleted since
+    base_gone: bool,
+    base_ahead: u32,
+    base_behind: u32,
+    committer_date: Option<u64>,
+    /// Pending `fixup!`, `squash!` and `amend!` commits, which autosquash would fold in
+    fixups: u32,
+    merged: bool,
+}
+
+pub fn run<T: Exec>(command: &T, options: &Options, verbose: bool) -> Result<(), Option<String>> {
+    let mut rows = get_rows(command, options, verbose)?;
+
+    rows.retain(|row| (!options.merged || row.merged) && (!options.no_merged || !row.merged));
+
+    sort_rows(&mut rows, &options.sort);
+
+    let output = match options.format {
+        OutputFormat::Table => format_table(&rows, now()),
+        OutputFormat::Json => serde_json::to_string_pretty(&rows)
+            .map_err(|err| format!("Failed to serialize branches: {}", err))?,
+    };
+
+    println!("{output}");
+
+    Ok(())
+}
+
+fn get_rows<T: Exec>(command: &T, options: &Options, verbose: bool) -> Result<Vec<Row>, String> {
+    let branches = parser::branches(command, &["refs/heads"], verbose)
+        .map_err(|()| "Failed to list local branches".to_string())?;
+
+    let recorded_bases = if options.recorded_bases {
+        get_recorded_bases(command, verbose)
+    } else {
+        Default::default()
+    };
+
+    let mut rows = Vec::new();
+
+    for branch in &branches {
+        let name = branch.name();
+
+        if let Some(pattern) = &options.pattern {
+            if !name.contains(pattern.as_str()) {
+                continue;
+            }
+        }
+
+        let base = recorded_bases.get(name).unwrap_or(&options.base);
+
+        let comparison = ahead_behind(command, &branch.refname, base, verbose).ok();
+        let (base_ahead, base_behind) = comparison.unwrap_or_default();
+        let fixups = match comparison {
+            Some(_) => count_fixups(command, &branch.refname, base, verbose)?,
+            None => 0,
+        };
+
+        rows.push(Row {
+            name: name.to_string(),
+            head: branch.head,
+            upstream: branch.upstream.as_ref().map(|upstream| {
+                upstream
+                    .strip_prefix("refs/remotes/")
+                    .unwrap_or(upstream)
+                    .to_string()
+            }),
+            upstream_gone: branch.track.gone,
+            upstream_ahead: branch.track.ahead,
+            upstream_behind: branch.track.behind,
+            base: base.to_string(),
+            base_gone: comparison.is_none(),
+            base_ahead,
+            base_behind,
+            committer_date: branch.committer_date,
+            fixups,
+            // Every commit of the branch is part of the base
+            merged: comparison.is_some() && name != base && base_ahead == 0,
+        });
+    }
+
+    Ok(rows)
+}
+
+fn count_fixups<T: Exec>(
+    command: &T,
+    refname: &str,
+    base: &str,
+    verbose: bool,
+) -> Result<u32, String> {
+    let count = command
+        .exec(
+            &[
+                "rev-list",
+                "--count",
+                "--extended-regexp",
+                "--grep=^(fixup|squash|amend)! ",
+                &format!("{base}..{refname}"),
+            ],
+            verbose,
+            false,
+        )
+        .map_err(|()| format!("Failed to count fixup commits of '{}'", refname))?;
+
+    Ok(count.trim().parse().unwrap_or(0))
+}
+
+fn sort_rows(rows: &mut [Row], sort: &BranchSort) {
+    rows.sort_by(|a, b| {
+        match sort {
+            BranchSort::Name => std::cmp::Ordering::Equal,
+            BranchSort::Age => b.committer_date.cmp(&a.committer_date),
+            BranchSort::Ahead => b.base_ahead.cmp(&a.base_ahead),
+            BranchSort::Behind => b.base_behind.cmp(&a.base_behind),
+        }
+        .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+fn format_table(rows: &[Row], now: u64) -> String {
+    if rows.is_empty() {
+        return "No branches found".to_string();
+    }
+
+    let mut table = vec![[
+        "", "BRANCH", "UPSTREAM", "SYNC", "BASE", "AGE", "FIXUPS", "MERGED",
+    ]
+    .map(String::from)];
+
+    for row in rows {
+        let (upstream, sync) = match &row.upstream {
+            Some(upstream) if row.upstream_gone => (format!("{upstream} (gone)"), "-".to_string()),
+            Some(upstream) => (
+                upstream.clone(),
+                format!("+{} -{}", row.upstream_ahead, row.upstream_behind),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+
+        table.push([
+            if row.head { "*" } else { "" }.to_string(),
+            row.name.clone(),
+            upstream,
+            sync,
+            match row.base_gone {
+                true => format!("{} (gone)", row.base),
+                false => format!("{} +{} -{}", row.base, row.base_ahead, row.base_behind),
+            },
+            row.committer_date
+                .map_or("-".to_string(), |date| format_age(now.saturating_sub(date))),
+            row.fixups.to_string(),
+            if row.merged { "yes" } else { "no" }.to_string(),
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..table[0].len())
+        .map(|column| {
+            table
+                .iter()
+                .map(|cells| cells[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    table
+        .iter()
+        .map(|cells| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn options() -> Options {
+        Options {
+            base: "main".to_string(),
+            recorded_bases: true,
+            pattern: None,
+            merged: false,
+            no_merged: false,
+            sort: BranchSort::Age,
+            format: OutputFormat::Table,
+        }
+    }
+
+    fn row(name: &str, committer_date: u64, base_ahead: u32) -> Row {
+        Row {
+            name: name.to_string(),
+            head: false,
+            upstream: None,
+            upstream_gone: false,
+            upstream_ahead: 0,
+            upstream_behind: 0,
+            base: "main".to_string(),
+            base_gone: false,
+            base_ahead,
+            base_behind: 0,
+            committer_date: Some(committer_date),
+            fixups: 0,
+            merged: base_ahead == 0,
+        }
+    }
+
+    #[test]
+    fn test_get_rows() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args[0] == "for-each-ref" && args[2] == "refs/heads" && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("*\0refs/heads/feature\0refs/remotes/origin/feature\0[ahead 1]\x001700000000\0Jane <jane@example.com>\0\n \
+                    \0refs/heads/fix\0\0\x001600000000\0John <john@example.com>\0\n".to_string())
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["config", "--get-regexp"]))
+            .times(1)
+            .returning(|_, _, _| Ok("branch.fix.lgitbase develop\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/heads/feature...main",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("3\t5\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/heads/fix...develop",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("0\t2\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args.starts_with(&["rev-list", "--count"]) && args[4] == "main..refs/heads/feature"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("2\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args.starts_with(&["rev-list", "--count"]) && args[4] == "develop..refs/heads/fix"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("0\n".to_string()));
+
+        let rows = get_rows(&command, &options(), false).unwrap();
+
+        assert_eq!(
+            rows[0],
+            Row {
+                name: "feature".to_string(),
+                head: true,
+                upstream: Some("origin/feature".to_string()),
+                upstream_gone: false,
+                upstream_ahead: 1,
+                upstream_behind: 0,
+                base: "main".to_string(),
+                base_gone: false,
+                base_ahead: 3,
+                base_behind: 5,
+                committer_date: Some(1_700_000_000),
+                fixups: 2,
+                merged: false,
+            }
+        );
+        assert_eq!(rows[1].base, "develop");
+        assert!(rows[1].merged);
+    }
+
+    #[test]
+    fn test_get_rows_with_deleted_base() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "for-each-ref")
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(
+                    " \0refs/heads/fix\0\0\0\x001600000000\0John <john@example.com>\0\n"
+                        .to_string(),
+                )
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["config", "--get-regexp"]))
+            .times(1)
+            .returning(|_, _, _| Ok("branch.fix.lgitbase develop\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "rev-list" && args[3] == "refs/heads/fix...develop")
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let rows = get_rows(&command, &options(), false).unwrap();
+
+        assert_eq!(rows[0].base, "develop");
+        assert!(rows[0].base_gone);
+        assert!(!rows[0].merged);
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let mut rows = vec![row("b", 10, 1), row("a", 20, 3), row("c", 20, 2)];
+
+        sort_rows(&mut rows, &BranchSort::Age);
+        assert_eq!(
+            rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>(),
+            ["a", "c", "b"]
+        );
+
+        sort_rows(&mut rows, &BranchSort::Ahead);
+        assert_eq!(
+            rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>(),
+            ["a", "c", "b"]
+        );
+
+        sort_rows(&mut rows, &BranchSort::Name);
+        assert_eq!(
+            rows.iter().map(|row| row.name.as_str()).collect::<Vec<_>>(),
+            ["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_format_table() {
+        let mut feature = row("feature", 0, 2);
+        feature.head = true;
+        feature.upstream = Some("origin/feature".to_string());
+        feature.upstream_ahead = 1;
+        feature.fixups = 1;
+
+        let mut old = row("old", 86_400, 0);
+        old.upstream = Some("origin/old".to_string());
+        old.upstream_gone = true;
+
+        assert_eq!(
+            format_table(&[feature, old], 2 * 86_400),
+            "   BRANCH   UPSTREAM           SYNC   BASE        AGE  FIXUPS  MERGED\n\
+             *  feature  origin/feature     +1 -0  main +2 -0  2d   1       no\n   \
+                old      origin/old (gone)  -      main +0 -0  1d   0       yes"
+        );
+    }
+
+    #[test]
+    fn test_format_json() {
+        let json = serde_json::to_string(&[row("fix", 1, 0)]).unwrap();
+
+        assert_eq!(
+            json,
+            "[{\"name\":\"fix\",\"head\":false,\"upstream\":null,\"upstream_gone\":false,\"upstream_ahead\":0,\"upstream_behind\":0,\"base\":\"main\",\"base_gone\":false,\"base_ahead\":0,\"base_behind\":0,\"committer_date\":1,\"fixups\":0,\"merged\":true}]"
+        );
+    }
+}
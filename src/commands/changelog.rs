@@ -0,0 +1,572 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::cli::ChangelogFormat;
+use crate::commands::Exec;
+use crate::config;
+use crate::conventional::{self, Header};
+use crate::parser::{self, Message};
+use crate::utils::get_remote;
+
+/// Trailers naming the issues a commit belongs to, e.g. `Refs: #12` or `Closes: ABC-34`
+const ISSUE_TRAILERS: [&str; 6] = ["refs", "closes", "fixes", "resolves", "issue", "issues"];
+
+/// Subject of the commits `lgit release` makes, followed by the tag
+pub const RELEASE_PREFIX: &str = "chore(release): ";
+
+/// Commits of a range grouped by type
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Changelog {
+    pub range: String,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Section {
+    /// Conventional Commits type, `other` for commits not following them
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub title: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub sha: String,
+    pub scope: Option<String>,
+    pub description: String,
+    /// What breaks, from the `BREAKING CHANGE` trailer or the description of a `!` commit
+    pub breaking: Option<String>,
+    pub issues: Vec<Issue>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Issue {
+    /// Issue reference as written, e.g. `#12` or `ABC-34`
+    pub id: String,
+    pub url: Option<String>,
+}
+
+impl Changelog {
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.sections.iter().flat_map(|section| &section.entries)
+    }
+
+    pub fn render(&self, format: &ChangelogFormat) -> Result<String, String> {
+        match format {
+            ChangelogFormat::Markdown => Ok(self.to_markdown()),
+            ChangelogFormat::Json => serde_json::to_string_pretty(self)
+                .map_err(|err| format!("Failed to serialize changelog: {}", err)),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let breaking: Vec<String> = self
+            .entries()
+            .filter_map(|entry| {
+                let note = entry.breaking.as_ref()?;
+
+                Some(format!("- {}{} ({})", bold_scope(entry), note, entry.sha))
+            })
+            .collect();
+
+        let mut blocks = Vec::new();
+
+        if !breaking.is_empty() {
+            blocks.push(format!("### Breaking Changes\n\n{}", breaking.join("\n")));
+        }
+
+        for section in &self.sections {
+            let lines: Vec<String> = section
+                .entries
+                .iter()
+                .map(|entry| {
+                    let mut description = entry.description.clone();
+                    let mut issues = Vec::new();
+
+                    // Linked where the description mentions them, e.g. `handle timeouts (#7)`
+                    for issue in &entry.issues {
+                        let mention =
+                            Regex::new(&format!(r"{}\b", regex::escape(&issue.id))).unwrap();
+
+                        if mention.is_match(&description) {
+                            description = mention.replace(&description, link(issue)).to_string();
+                        } else {
+                            issues.push(link(issue));
+                        }
+                    }
+
+                    let mut line = format!("- {}{}", bold_scope(entry), description);
+
+                    if !issues.is_empty() {
+                        line.push_str(&format!(" ({})", issues.join(", ")));
+                    }
+
+                    line.push_str(&format!(" ({})", entry.sha));
+
+                    line
+                })
+                .collect();
+
+            blocks.push(format!("### {}\n\n{}", section.title, lines.join("\n")));
+        }
+
+        if blocks.is_empty() {
+            return format!("No changes in {}", self.range);
+        }
+
+        blocks.join("\n\n")
+    }
+}
+
+pub fn run<T: Exec>(
+    command: &T,
+    range: Option<String>,
+    format: &ChangelogFormat,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let range = match range {
+        Some(range) => range,
+        None => default_range(command, verbose),
+    };
+
+    let changelog = generate(command, &range, verbose)?;
+
+    println!("{}", changelog.render(format)?);
+
+    Ok(())
+}
+
+/// The latest tag reachable from HEAD to HEAD, or the whole history when there is no tag yet
+pub fn default_range<T: Exec>(command: &T, verbose: bool) -> String {
+    match latest_tag(command, verbose) {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    }
+}
+
+pub fn latest_tag<T: Exec>(command: &T, verbose: bool) -> Option<String> {
+    command
+        .exec(&["describe", "--tags", "--abbrev=0"], verbose, false)
+        .ok()
+        .map(|tag| tag.trim().to_string())
+}
+
+/// Groups the commits of `range` (e.g. `v1.2.0..HEAD`) by type, in the order of
+/// `lgit.commitTypes`, skipping merges, fixups and cherry-picks of commits already listed
+pub fn generate<T: Exec>(command: &T, range: &str, verbose: bool) -> Result<Changelog, String> {
+    let messages = parser::messages(command, &["--no-merges", range], verbose)
+        .map_err(|()| format!("Failed to list commits in '{}'", range))?;
+
+    let template = issue_template(command, verbose);
+
+    let entries: Vec<(String, Entry)> = dedupe(&messages)
+        .into_iter()
+        .filter_map(|message| entry(message, template.as_ref()))
+        .collect();
+
+    let mut kinds = conventional::types(command, verbose);
+
+    for (kind, _) in &entries {
+        if !kinds.contains(kind) && kind != "other" {
+            kinds.push(kind.clone());
+        }
+    }
+
+    kinds.push("other".to_string());
+
+    let mut remaining = entries;
+    let mut sections = Vec::new();
+
+    for kind in kinds {
+        let (mut matching, rest): (Vec<_>, Vec<_>) = remaining
+            .into_iter()
+            .partition(|(entry_kind, _)| *entry_kind == kind);
+        remaining = rest;
+
+        if matching.is_empty() {
+            continue;
+        }
+
+        // Stable, so commits of the same scope keep the order of the log
+        matching.sort_by(|(_, a), (_, b)| a.scope.cmp(&b.scope));
+
+        sections.push(Section {
+            title: title(&kind),
+            kind,
+            entries: matching.into_iter().map(|(_, entry)| entry).collect(),
+        });
+    }
+
+    Ok(Changelog {
+        range: range.to_string(),
+        sections,
+    })
+}
+
+/// Drops commits cherry-picked from one already listed (recorded by `cherry-pick -x` as
+/// `(cherry picked from commit <sha>)`), keeping the oldest of each
+fn dedupe(messages: &[Message]) -> Vec<&Message> {
+    let regex = Regex::new(r"\(cherry picked from commit ([0-9a-f]+)\)").unwrap();
+    let mut seen: Vec<&str> = Vec::new();
+    let mut kept = Vec::new();
+
+    // The log lists the newest commits first
+    for message in messages.iter().rev() {
+        let picked: Vec<&str> = regex
+            .captures_iter(&message.message)
+            .filter_map(|captures| captures.get(1))
+            .map(|sha| sha.as_str())
+            .collect();
+
+        let duplicate = std::iter::once(message.sha.as_str())
+            .chain(picked.iter().copied())
+            .any(|sha| {
+                seen.iter()
+                    .any(|seen| seen.starts_with(sha) || sha.starts_with(seen))
+            });
+
+        seen.push(&message.sha);
+        seen.extend(picked);
+
+        if !duplicate {
+            kept.push(message);
+        }
+    }
+
+    kept.reverse();
+
+    kept
+}
+
+fn entry(message: &Message, template: Option<&IssueTemplate>) -> Option<(String, Entry)> {
+    let subject = message.message.lines().next().unwrap_or_default().trim();
+
+    // Version bumps committed by `lgit release` say nothing about what changed
+    if subject.is_empty() || conventional::is_fixup(subject) || subject.starts_with(RELEASE_PREFIX)
+    {
+        return None;
+    }
+
+    let trailers = conventional::trailers(&message.message);
+    let breaking_change = trailers
+        .iter()
+        .find(|(key, _)| key == "BREAKING CHANGE" || key == "BREAKING-CHANGE")
+        .map(|(_, value)| value.clone());
+
+    let (kind, scope, description, breaking) = match Header::parse(subject) {
+        Some(header) => {
+            let breaking = match (breaking_change, header.breaking) {
+                (Some(note), _) => Some(note),
+                (None, true) => Some(header.description.clone()),
+                (None, false) => None,
+            };
+
+            (header.kind, header.scope, header.description, breaking)
+        }
+        None => ("other".to_string(), None, subject.to_string(), None),
+    };
+
+    Some((
+        kind,
+        Entry {
+            sha: message.sha[..message.sha.len().min(7)].to_string(),
+            scope,
+            description,
+            breaking,
+            issues: issues(&message.message, &trailers)
+                .into_iter()
+                .map(|id| Issue {
+                    url: issue_url(template, &id),
+                    id,
+                })
+                .collect(),
+        },
+    ))
+}
+
+/// Issue references in a message: `#12` anywhere, and the values of trailers such as `Refs`
+fn issues(message: &str, trailers: &[(String, String)]) -> Vec<String> {
+    let hash = Regex::new(r"(?:^|[\s(\[,])(#\d+)\b").unwrap();
+    let key = Regex::new(r"^(#\d+|[A-Z][A-Z0-9]+-\d+)$").unwrap();
+
+    let mut found: Vec<String> = Vec::new();
+
+    let from_trailers = trailers
+        .iter()
+        .filter(|(name, _)| ISSUE_TRAILERS.contains(&name.to_lowercase().as_str()))
+        .flat_map(|(_, value)| value.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|id| key.is_match(id))
+        .map(|id| id.to_string());
+
+    let from_text = hash
+        .captures_iter(message)
+        .map(|captures| captures[1].to_string());
+
+    for id in from_text.chain(from_trailers) {
+        if !found.contains(&id) {
+            found.push(id);
+        }
+    }
+
+    found
+}
+
+/// URL of issues with an `{id}` placeholder
+#[derive(Debug, PartialEq)]
+enum IssueTemplate {
+    /// Set in `lgit.issueUrl`
+    Configured(String),
+    /// Guessed from the remote for GitHub and GitLab
+    Forge(String),
+}
+
+fn issue_template<T: Exec>(command: &T, verbose: bool) -> Option<IssueTemplate> {
+    if let Some(template) = config::get(command, "lgit.issueUrl", verbose) {
+        return Some(IssueTemplate::Configured(template));
+    }
+
+    let remote = get_remote(command, verbose);
+    let url = command
+        .exec(&["remote", "get-url", &remote], verbose, false)
+        .ok()?;
+
+    forge_template(url.trim()).map(IssueTemplate::Forge)
+}
+
+fn forge_template(url: &str) -> Option<String> {
+    // git@github.com:owner/repo.git, ssh://git@github.com/owner/repo or https://github.com/owner/repo
+    let regex =
+        Regex::new(r"^(?:[a-z+]+://)?(?:[^@/]+@)?([^:/]+)(?::\d+)?[:/](.+?)(?:\.git)?/?$").unwrap();
+    let captures = regex.captures(url)?;
+    let (host, path) = (&captures[1], &captures[2]);
+
+    if host.contains("github") {
+        Some(format!("https://{host}/{path}/issues/{{id}}"))
+    } else if host.contains("gitlab") {
+        Some(format!("https://{host}/{path}/-/issues/{{id}}"))
+    } else {
+        None
+    }
+}
+
+/// Links `#12` style references with the guessed forge URL, and every reference with a configured
+/// `lgit.issueUrl`, which may point to another tracker
+fn issue_url(template: Option<&IssueTemplate>, id: &str) -> Option<String> {
+    match (template?, id.strip_prefix('#')) {
+        (IssueTemplate::Configured(template) | IssueTemplate::Forge(template), Some(number)) => {
+            Some(template.replace("{id}", number))
+        }
+        (IssueTemplate::Configured(template), None) => Some(template.replace("{id}", id)),
+        // Tracker keys such as ABC-34 aren't issues of the forge
+        (IssueTemplate::Forge(_), None) => None,
+    }
+}
+
+fn link(issue: &Issue) -> String {
+    match &issue.url {
+        Some(url) => format!("[{}]({})", issue.id, url),
+        None => issue.id.clone(),
+    }
+}
+
+fn bold_scope(entry: &Entry) -> String {
+    match &entry.scope {
+        Some(scope) => format!("**{scope}:** "),
+        None => String::new(),
+    }
+}
+
+fn title(kind: &str) -> String {
+    match kind {
+        "feat" => "Features".to_string(),
+        "fix" => "Bug Fixes".to_string(),
+        "perf" => "Performance".to_string(),
+        "docs" => "Documentation".to_string(),
+        "refactor" => "Refactoring".to_string(),
+        "test" => "Tests".to_string(),
+        "build" => "Build".to_string(),
+        "ci" => "Continuous Integration".to_string(),
+        "style" => "Style".to_string(),
+        "chore" => "Chores".to_string(),
+        "revert" => "Reverts".to_string(),
+        "other" => "Other Changes".to_string(),
+        _ => {
+            let mut chars = kind.chars();
+
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn message(sha: &str, message: &str) -> Message {
+        Message {
+            sha: sha.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dedupe_cherry_picks() {
+        let messages = vec![
+            message(
+                "ccc3333333",
+                "fix: handle empty input\n\n(cherry picked from commit aaa1111111)\n",
+            ),
+            message("bbb2222222", "feat: add login\n"),
+            message("aaa1111111", "fix: handle empty input\n"),
+        ];
+
+        let kept: Vec<&str> = dedupe(&messages)
+            .iter()
+            .map(|message| message.sha.as_str())
+            .collect();
+
+        assert_eq!(kept, ["bbb2222222", "aaa1111111"]);
+    }
+
+    #[test]
+    fn test_issues() {
+        let text = "fix: handle empty input (#12)\n\nSee #13 and issue#14.\n\nRefs: #12, ABC-34\n";
+
+        assert_eq!(
+            issues(text, &conventional::trailers(text)),
+            ["#12", "#13", "ABC-34"]
+        );
+    }
+
+    #[test]
+    fn test_forge_template() {
+        assert_eq!(
+            forge_template("git@github.com:owner/repo.git"),
+            Some("https://github.com/owner/repo/issues/{id}".to_string())
+        );
+        assert_eq!(
+            forge_template("https://gitlab.com/group/sub/repo"),
+            Some("https://gitlab.com/group/sub/repo/-/issues/{id}".to_string())
+        );
+        assert_eq!(
+            forge_template("ssh://git@github.com:22/owner/repo.git"),
+            Some("https://github.com/owner/repo/issues/{id}".to_string())
+        );
+        assert_eq!(forge_template("/srv/git/repo.git"), None);
+    }
+
+    #[test]
+    fn test_issue_url() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[..2] == ["config", "--get"])
+            .times(2)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["remote", "get-url", "origin"])
+            .times(1)
+            .returning(|_, _, _| Ok("git@github.com:owner/repo.git\n".to_string()));
+
+        let forge = issue_template(&command, false);
+
+        assert_eq!(
+            forge,
+            Some(IssueTemplate::Forge(
+                "https://github.com/owner/repo/issues/{id}".to_string()
+            ))
+        );
+        assert_eq!(
+            issue_url(forge.as_ref(), "#12"),
+            Some("https://github.com/owner/repo/issues/12".to_string())
+        );
+        assert_eq!(issue_url(forge.as_ref(), "ABC-34"), None);
+
+        let configured =
+            IssueTemplate::Configured("https://jira.example.com/browse/{id}".to_string());
+
+        assert_eq!(
+            issue_url(Some(&configured), "ABC-34"),
+            Some("https://jira.example.com/browse/ABC-34".to_string())
+        );
+        assert_eq!(issue_url(None, "#12"), None);
+    }
+
+    #[test]
+    fn test_generate_and_render() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == [
+                    "log",
+                    "-z",
+                    "--format=%H%x00%B",
+                    "--no-merges",
+                    "v1.0.0..HEAD",
+                ] && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("aaa1111111\0feat(parser)!: drop the legacy format\n\n\
+                    BREAKING CHANGE: version 1 files no longer load\nRefs: #12\n\0\n\
+                    bbb2222222\0Update the readme\n\0\n\
+                    ccc3333333\0fix: handle empty input (#7)\n\0\n\
+                    ddd4444444\0feat(cli): add a changelog command\n\0\n\
+                    eee5555555\0feat: add a progress bar\n\0\n\
+                    fff6666666\0chore(release): v1.0.0\n\0"
+                    .to_string())
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.issueUrl"])
+            .times(1)
+            .returning(|_, _, _| Ok("https://issues.example.com/{id}\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.commitTypes"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let changelog = generate(&command, "v1.0.0..HEAD", false).unwrap();
+
+        assert_eq!(
+            changelog.render(&ChangelogFormat::Markdown),
+            Ok("### Breaking Changes\n\n\
+                - **parser:** version 1 files no longer load (aaa1111)\n\n\
+                ### Features\n\n\
+                - add a progress bar (eee5555)\n\
+                - **cli:** add a changelog command (ddd4444)\n\
+                - **parser:** drop the legacy format ([#12](https://issues.example.com/12)) (aaa1111)\n\n\
+                ### Bug Fixes\n\n\
+                - handle empty input ([#7](https://issues.example.com/7)) (ccc3333)\n\n\
+                ### Other Changes\n\n\
+                - Update the readme (bbb2222)"
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_empty() {
+        let changelog = Changelog {
+            range: "v1.0.0..HEAD".to_string(),
+            sections: Vec::new(),
+        };
+
+        assert_eq!(
+            changelog.render(&ChangelogFormat::Markdown),
+            Ok("No changes in v1.0.0..HEAD".to_string())
+        );
+        assert_eq!(
+            changelog.render(&ChangelogFormat::Json),
+            Ok("{\n  \"range\": \"v1.0.0..HEAD\",\n  \"sections\": []\n}".to_string())
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::process;
+
+use crate::commands::Exec;
+use crate::parser::{self, Commit};
+
+pub struct Options {
+    pub base: String,
+    /// Shell command run in the worktree of each commit, passing when it exits successfully
+    pub exec: String,
+    pub fail_fast: bool,
+}
+
+/// Runs a command on every commit of the current branch since `base`, each checked out in a
+/// temporary worktree so that the working tree stays untouched, and reports which ones pass
+pub fn run<T: Exec>(command: &T, options: &Options, verbose: bool) -> Result<(), Option<String>> {
+    let commits = parser::commits(
+        command,
+        &["--reverse", &format!("{}..HEAD", options.base)],
+        verbose,
+    )
+    .map_err(|()| format!("Failed to list commits since '{}'", options.base))?;
+
+    if commits.is_empty() {
+        println!("No commits since {}", options.base);
+        return Ok(());
+    }
+
+    let worktree = std::env::temp_dir()
+        .join(format!("lgit-check-{}", process::id()))
+        .to_string_lossy()
+        .to_string();
+
+    command
+        .exec(
+            &["worktree", "add", "--detach", "--quiet", &worktree, "HEAD"],
+            verbose,
+            false,
+        )
+        .map_err(|()| format!("Failed to create a worktree at '{}'", worktree))?;
+
+    let results = check_commits(
+        command,
+        &commits,
+        &worktree,
+        options.fail_fast,
+        |dir| run_check(&options.exec, dir, verbose),
+        verbose,
+    );
+
+    // With `--fail-fast` the worktree is left at the failing commit for investigating it
+    let stopped_at = match &results {
+        Ok(passed) if options.fail_fast => passed
+            .iter()
+            .position(|passed| !passed)
+            .map(|index| &commits[index]),
+        _ => None,
+    };
+
+    if stopped_at.is_none() {
+        // Forced, as the command may leave build output or other changes behind
+        let _ = command.exec(
+            &["worktree", "remove", "--force", &worktree],
+            verbose,
+            false,
+        );
+    }
+
+    let passed = results?;
+    let failed = passed.iter().filter(|passed| !**passed).count();
+    let unchecked = commits.len() - passed.len();
+
+    if failed == 0 {
+        println!("All {} commits passed", commits.len());
+        return Ok(());
+    }
+
+    let mut message = format!(
+        "{} of {} commits failed '{}'",
+        failed,
+        commits.len(),
+        options.exec
+    );
+
+    if unchecked > 0 {
+        message.push_str(&format!(", {unchecked} not checked"));
+    }
+
+    if let Some(commit) = stopped_at {
+        message.push_str(&format!(
+            "\n\nStopped at {sha}, still checked out in {worktree}. Fix it with \
+             'git rebase --interactive {sha}^' (mark it as edit), then remove the worktree with \
+             'git worktree remove --force {worktree}'",
+            sha = commit.sha
+        ));
+    }
+
+    Err(Some(message))
+}
+
+/// Checks out each commit in `worktree` and runs `check` there, printing the outcome as it goes.
+/// Returns whether each checked commit passed.
+fn check_commits<T: Exec>(
+    command: &T,
+    commits: &[Commit],
+    worktree: &str,
+    fail_fast: bool,
+    check: impl Fn(&str) -> Result<(), String>,
+    verbose: bool,
+) -> Result<Vec<bool>, String> {
+    let mut passed = Vec::new();
+
+    for commit in commits {
+        // Forced, as the check may have changed files, e.g. a build rewriting Cargo.lock, and the
+        // worktree belongs to lgit
+        command
+            .exec(
+                &[
+                    "-C",
+                    worktree,
+                    "checkout",
+                    "--quiet",
+                    "--force",
+                    "--detach",
+                    &commit.sha,
+                ],
+                verbose,
+                false,
+            )
+            .map_err(|()| format!("Failed to checkout commit {}", commit.sha))?;
+
+        match check(worktree) {
+            Ok(()) => {
+                println!("PASS {commit}");
+                passed.push(true);
+            }
+            Err(output) => {
+                println!("FAIL {commit}");
+
+                if !output.is_empty() {
+                    println!("{}", output.trim_end());
+                }
+
+                passed.push(false);
+
+                if fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(passed)
+}
+
+/// Runs `exec` through the shell in `dir`, returning its output when it fails
+fn run_check(exec: &str, dir: &str, verbose: bool) -> Result<(), String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+
+    if verbose {
+        println!("Executing: {exec}\n");
+    }
+
+    let output = process::Command::new(shell)
+        .args([flag, exec])
+        .current_dir(dir)
+        .output()
+        .map_err(|err| format!("Failed to run '{}': {}", exec, err))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn commits() -> Vec<Commit> {
+        ["abc123", "def456", "fed789"]
+            .iter()
+            .map(|sha| Commit {
+                sha: sha.to_string(),
+                subject: format!("Commit {sha}"),
+            })
+            .collect()
+    }
+
+    fn expect_checkouts(command: &mut MockCmd, times: usize) {
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args.starts_with(&[
+                    "-C",
+                    "/tmp/check",
+                    "checkout",
+                    "--quiet",
+                    "--force",
+                    "--detach",
+                ]) && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(times)
+            .returning(|_, _, _| Ok(String::new()));
+    }
+
+    #[test]
+    fn test_check_commits() {
+        let mut command = MockCmd::new();
+        expect_checkouts(&mut command, 3);
+
+        let checked = std::cell::Cell::new(0);
+        let passed = check_commits(
+            &command,
+            &commits(),
+            "/tmp/check",
+            false,
+            |dir| {
+                assert_eq!(dir, "/tmp/check");
+                checked.set(checked.get() + 1);
+
+                if checked.get() == 2 {
+                    Err("test failed".to_string())
+                } else {
+                    Ok(())
+                }
+            },
+            false,
+        );
+
+        assert_eq!(passed, Ok(vec![true, false, true]));
+    }
+
+    #[test]
+    fn test_check_commits_fail_fast() {
+        let mut command = MockCmd::new();
+        expect_checkouts(&mut command, 1);
+
+        let passed = check_commits(
+            &command,
+            &commits(),
+            "/tmp/check",
+            true,
+            |_| Err(String::new()),
+            false,
+        );
+
+        assert_eq!(passed, Ok(vec![false]));
+    }
+
+    #[test]
+    fn test_run_without_commits() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "log" && args[args.len() - 1] == "main..HEAD")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let options = Options {
+            base: "main".to_string(),
+            exec: "make test".to_string(),
+            fail_fast: false,
+        };
+
+        assert_eq!(run(&command, &options, false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_fail_fast_keeps_worktree() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "log")
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\0Broken commit\0".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["worktree", "add"]))
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[2] == "checkout" && args[6] == "abc123")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let options = Options {
+            base: "main".to_string(),
+            exec: "exit 1".to_string(),
+            fail_fast: true,
+        };
+
+        let message = run(&command, &options, false).unwrap_err().unwrap();
+
+        assert!(message.starts_with("1 of 1 commits failed 'exit 1'\n\nStopped at abc123"));
+        assert!(message.contains("git rebase --interactive abc123^"));
+    }
+
+    #[test]
+    fn test_run_check() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+
+        assert_eq!(run_check("exit 0", dir, false), Ok(()));
+        assert_eq!(
+            run_check("echo broken; exit 1", dir, false),
+            Err("broken\n".to_string())
+        );
+    }
+}
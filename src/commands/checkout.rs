@@ -1,91 +1,403 @@
+use std::io::{self, IsTerminal};
+
 use dialoguer::theme::ColorfulTheme;
-use dialoguer::FuzzySelect;
+use dialoguer::{Confirm, FuzzySelect};
+
+use crate::commands::{worktree, Exec};
+use crate::config;
+use crate::parser;
+use crate::utils::{
+    ahead_behind, get_current_branch, get_remote, is_ancestor, push_stash, ref_exists, stash,
+    unstash,
+};
 
-use crate::commands::Exec;
+/// Prefix of the message of stashes parked by lgit when leaving a branch
+pub const AUTOSTASH_PREFIX: &str = "lgit-autostash:";
+
+#[derive(Default)]
+pub struct Options {
+    pub remote: bool,
+    pub all: bool,
+    pub recent: Option<usize>,
+    pub carry: bool,
+    pub autostash: bool,
+    /// Pull request to check out, `Some(None)` to pick from the already fetched ones
+    pub pr: Option<Option<u32>>,
+    /// Open the branch in its own worktree and print its path instead of switching branches
+    pub worktree: bool,
+}
 
 pub fn run<T: Exec>(
     cmd: &T,
     name: Option<String>,
-    remote: bool,
-    all: bool,
+    options: &Options,
     verbose: bool,
 ) -> Result<(), Option<String>> {
-    if let Some(name) = name {
-        return do_checkout(cmd, &name, verbose);
+    if let Some(number) = options.recent {
+        println!("{}", list_recent(cmd, number, verbose)?);
+        return Ok(());
     }
 
-    let branch = get_branches(cmd, remote, all, verbose)?;
+    let branch = match (name, options.pr) {
+        (_, Some(Some(number))) => fetch_pull_request(cmd, number, verbose)?,
+        (Some(name), _) => name,
+        (None, pr) => get_branches(cmd, options.remote, options.all, pr.is_some(), verbose)?,
+    };
+
+    if options.worktree {
+        // Printed alone so that `cd "$(lgit co --worktree)"` works
+        let path = match get_local_name(cmd, &branch, verbose)? {
+            Some(local) => worktree::open(cmd, &local, Some(&branch), verbose)?,
+            None => worktree::open(cmd, &branch, None, verbose)?,
+        };
+
+        println!("{path}");
+
+        return Ok(());
+    }
 
-    do_checkout(cmd, &branch, verbose)
+    switch(cmd, &branch, options, verbose)
+}
+
+/// Fetches the head of a pull request into `refs/remotes/<remote>/pr/<number>`, trying GitHub's
+/// `refs/pull/<number>/head` first and GitLab's `refs/merge-requests/<number>/head` second, and
+/// brings the local `pr/<number>` branch up to date with it
+fn fetch_pull_request<T: Exec>(cmd: &T, number: u32, verbose: bool) -> Result<String, String> {
+    let remote = get_remote(cmd, verbose);
+    let branch = format!("pr/{number}");
+    let head = format!("refs/remotes/{remote}/{branch}");
+
+    let fetched = ["pull", "merge-requests"].iter().any(|forge_ref| {
+        // Forced, as pull requests are routinely rebased and force-pushed
+        let refspec = format!("+refs/{forge_ref}/{number}/head:{head}");
+
+        cmd.exec(&["fetch", &remote, &refspec], verbose, false)
+            .is_ok()
+    });
+
+    if !fetched {
+        return Err(format!(
+            "Failed to fetch pull request #{} from '{}' (neither refs/pull/{}/head nor refs/merge-requests/{}/head could be fetched)",
+            number, remote, number, number
+        ));
+    }
+
+    update_pull_request_branch(cmd, &branch, &head, verbose)?;
+
+    Ok(branch)
+}
+
+/// Creates `branch` at the fetched `head` of its pull request or fast-forwards it, keeping commits
+/// made on top of it during review
+fn update_pull_request_branch<T: Exec>(
+    cmd: &T,
+    branch: &str,
+    head: &str,
+    verbose: bool,
+) -> Result<(), String> {
+    let local = format!("refs/heads/{branch}");
+
+    if !ref_exists(cmd, &local, verbose) {
+        cmd.exec(&["branch", "--no-track", branch, head], verbose, false)
+            .map_err(|()| format!("Failed to create branch '{}'", branch))?;
+
+        return Ok(());
+    }
+
+    if is_ancestor(cmd, head, &local, verbose) {
+        return Ok(());
+    }
+
+    if !is_ancestor(cmd, &local, head, verbose) {
+        println!(
+            "{} has diverged from the pull request, which was probably force-pushed, and is kept as is (the pull request is at {})",
+            branch,
+            head.strip_prefix("refs/remotes/").unwrap_or(head)
+        );
+
+        return Ok(());
+    }
+
+    // git refuses to move the checked out branch any other way
+    let args = if get_current_branch(cmd, verbose).as_deref() == Some(branch) {
+        ["merge", "--ff-only", head].to_vec()
+    } else {
+        ["branch", "--force", branch, head].to_vec()
+    };
+
+    cmd.exec(&args, verbose, false)
+        .map_err(|()| format!("Failed to fast-forward '{}' to the pull request", branch))?;
+
+    Ok(())
+}
+
+/// Checks out `branch`, either carrying uncommitted changes along or parking them in a stash
+/// tagged with the branch they belong to, which is re-applied once that branch is checked out again
+fn switch<T: Exec>(
+    cmd: &T,
+    branch: &str,
+    options: &Options,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    if options.carry {
+        let stashed = stash(cmd, "checkout", verbose)?;
+        let result = do_checkout(cmd, branch, verbose);
+
+        if let Some(sha) = stashed {
+            unstash(cmd, &sha, verbose)?;
+        }
+
+        return result;
+    }
+
+    let parked = park_changes(cmd, options.autostash, verbose)?;
+
+    if let Err(err) = do_checkout(cmd, branch, verbose) {
+        if let Some(sha) = parked {
+            unstash(cmd, &sha, verbose)?;
+        }
+
+        return Err(err);
+    }
+
+    restore_parked_changes(cmd, verbose)
+}
+
+/// Returns the SHA of the stash the changes were parked in, if they were
+fn park_changes<T: Exec>(
+    cmd: &T,
+    autostash: bool,
+    verbose: bool,
+) -> Result<Option<String>, String> {
+    let status = cmd
+        .exec(&["status", "--porcelain"], verbose, false)
+        .map_err(|()| "Failed to retrieve git status (check if in git repository)".to_string())?;
+
+    if status.is_empty() {
+        return Ok(None);
+    }
+
+    let Some(current) = get_current_branch(cmd, verbose) else {
+        return Ok(None);
+    };
+
+    // `lgit.autostash` set to true or false skips the question, which scripts can't answer and
+    // where changes are therefore carried along as plain `git checkout` does
+    let park = autostash
+        || match config::get_bool(cmd, "lgit.autostash", verbose) {
+            Some(park) => park,
+            None if !io::stdin().is_terminal() => false,
+            None => Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "Stash uncommitted changes on '{current}' until you return to it?"
+                ))
+                .default(true)
+                .interact()
+                .map_err(|err| format!("Failed to confirm stash: {}", err))?,
+        };
+
+    if !park {
+        return Ok(None);
+    }
+
+    let message = format!("{AUTOSTASH_PREFIX}{current}");
+
+    let Some(sha) = push_stash(cmd, &message, verbose)
+        .map_err(|()| format!("Failed to stash uncommitted changes on '{}'", current))?
+    else {
+        return Ok(None);
+    };
+
+    println!("Stashed uncommitted changes on {current}");
+
+    Ok(Some(sha))
+}
+
+fn restore_parked_changes<T: Exec>(cmd: &T, verbose: bool) -> Result<(), Option<String>> {
+    let Some(current) = get_current_branch(cmd, verbose) else {
+        return Ok(());
+    };
+
+    let suffix = format!(": {AUTOSTASH_PREFIX}{current}");
+
+    let stashes =
+        parser::stashes(cmd, verbose).map_err(|()| "Failed to list stashes".to_string())?;
+
+    let Some(parked) = stashes
+        .iter()
+        .find(|stash| stash.subject.ends_with(&suffix))
+    else {
+        return Ok(());
+    };
+
+    let status = cmd
+        .exec(&["status", "--porcelain"], verbose, false)
+        .map_err(|()| "Failed to retrieve git status (check if in git repository)".to_string())?;
+
+    // Changes carried over from the previous branch would otherwise be mixed with the parked ones
+    if !status.is_empty() {
+        println!(
+            "Changes stashed on {} are kept in {} as the working tree isn't clean, restore them with 'git stash pop {}'",
+            current, parked.reference, parked.reference
+        );
+
+        return Ok(());
+    }
+
+    cmd.exec(&["stash", "pop", &parked.reference], verbose, false)
+        .map_err(|()| {
+            format!(
+                "Failed to restore changes stashed on '{}' (they are kept in {})",
+                current, parked.reference
+            )
+        })?;
+
+    println!("Restored uncommitted changes on {current}");
+
+    Ok(())
 }
 
 fn do_checkout<T: Exec>(cmd: &T, branch: &str, verbose: bool) -> Result<(), Option<String>> {
+    if let Some(local) = get_local_name(cmd, branch, verbose)? {
+        return checkout_remote(cmd, branch, &local, verbose);
+    }
+
     cmd.exec(&["checkout", branch], verbose, false)
         .map_err(|()| format!("Failed to checkout branch '{}'", branch))?;
 
     Ok(())
 }
 
+/// Returns the local branch name for a remote-tracking branch such as `origin/feature`, or `None`
+/// if `branch` doesn't name a remote-tracking branch
+fn get_local_name<T: Exec>(cmd: &T, branch: &str, verbose: bool) -> Result<Option<String>, String> {
+    if !branch.contains('/') || ref_exists(cmd, &format!("refs/heads/{branch}"), verbose) {
+        return Ok(None);
+    }
+
+    let remotes = cmd
+        .exec(&["remote"], verbose, false)
+        .map_err(|()| "Failed to get git remotes (check network connection)".to_string())?;
+
+    for remote in remotes.lines() {
+        if let Some(local) = branch.strip_prefix(&format!("{remote}/")) {
+            if ref_exists(cmd, &format!("refs/remotes/{branch}"), verbose) {
+                return Ok(Some(local.to_string()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks out the local counterpart of a remote-tracking branch, creating it with `--track` when
+/// it doesn't exist yet instead of relying on git's guessing, which fails when several remotes
+/// have a branch of the same name
+fn checkout_remote<T: Exec>(
+    cmd: &T,
+    remote_branch: &str,
+    local: &str,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    if !ref_exists(cmd, &format!("refs/heads/{local}"), verbose) {
+        cmd.exec(&["checkout", "--track", remote_branch], verbose, false)
+            .map_err(|()| {
+                format!(
+                    "Failed to create branch '{}' tracking '{}'",
+                    local, remote_branch
+                )
+            })?;
+
+        return Ok(());
+    }
+
+    cmd.exec(&["checkout", local], verbose, false)
+        .map_err(|()| format!("Failed to checkout branch '{}'", local))?;
+
+    let (ahead, behind) = ahead_behind(
+        cmd,
+        &format!("refs/heads/{local}"),
+        &format!("refs/remotes/{remote_branch}"),
+        verbose,
+    )
+    .map_err(|()| format!("Failed to compare '{}' with '{}'", local, remote_branch))?;
+
+    match (ahead, behind) {
+        (0, 0) => {}
+        (_, 0) => println!("Branch '{local}' is {ahead} commits ahead of '{remote_branch}'"),
+        // Scripts can't answer the question
+        (0, _) if !io::stdin().is_terminal() => println!(
+            "Warning: branch '{local}' is {behind} commits behind '{remote_branch}', fast-forward it with 'git merge --ff-only {remote_branch}'"
+        ),
+        (0, _) => {
+            let fast_forward = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!(
+                    "Branch '{local}' is {behind} commits behind '{remote_branch}', fast-forward it?"
+                ))
+                .default(true)
+                .interact()
+                .map_err(|err| format!("Failed to confirm fast-forward: {}", err))?;
+
+            if fast_forward {
+                cmd.exec(&["merge", "--ff-only", remote_branch], verbose, false)
+                    .map_err(|()| format!("Failed to fast-forward '{}' to '{}'", local, remote_branch))?;
+            }
+        }
+        _ => println!(
+            "Warning: branch '{local}' has diverged from '{remote_branch}' ({ahead} ahead, {behind} behind)"
+        ),
+    }
+
+    Ok(())
+}
+
 fn get_branches<T: Exec>(
     cmd: &T,
     remote: bool,
     all: bool,
+    pr: bool,
     verbose: bool,
 ) -> Result<String, String> {
-    let remotes: Vec<String> = cmd
-        .exec(&["remote"], verbose, false)
-        .map_err(|()| "Failed to get git remotes (check network connection)".to_string())?
-        .lines()
-        .map(String::from)
-        .collect();
-
     // Determine which branches to list based on flags
-    let branch_args = if all {
-        vec!["branch", "-a", "--format", "%(refname)"]
+    let (patterns, branch_type): (&[&str], &str) = if pr {
+        // Remote ones, fetched by `--pr` or a `refs/pull/*/head` refspec, are checked out as
+        // tracking branches like any remote branch
+        (
+            &["refs/heads/pr", "refs/remotes/*/pr/*"],
+            "pull request branches",
+        )
+    } else if all {
+        (
+            &["refs/heads", "refs/remotes"],
+            "branches (local and remote)",
+        )
     } else if remote {
-        vec!["branch", "-r", "--format", "%(refname)"]
+        (&["refs/remotes"], "remote branches")
     } else {
-        vec!["branch", "--format", "%(refname)"]
+        (&["refs/heads"], "local branches")
     };
 
-    let mut branches: Vec<String> = cmd
-        .exec(&branch_args, verbose, false)
-        .map_err(|()| {
-            let branch_type = if all {
-                "all branches (local and remote)"
-            } else if remote {
-                "remote branches"
-            } else {
-                "local branches"
-            };
-            format!("Failed to list {}", branch_type)
-        })?
-        .lines()
-        .map(|line| {
-            let mut line = String::from(line);
-
-            // Clean up remote prefixes
-            for remote in &remotes {
-                line = line.replace(&format!("refs/remotes/{}/", &remote), "");
-            }
-
-            // Clean up local branch prefix
-            line.replace("refs/heads/", "").trim().to_string()
+    let branches: Vec<(String, u64)> = parser::branches(cmd, patterns, verbose)
+        .map_err(|()| format!("Failed to list {}", branch_type))?
+        .iter()
+        .filter(|branch| !branch.head)
+        // Remote branches keep their remote prefix, so that the selected remote is the one tracked
+        .map(|branch| {
+            (
+                branch.name().to_string(),
+                branch.committer_date.unwrap_or(0),
+            )
         })
-        .filter(|branch| branch != "HEAD")
+        .filter(|(branch, _)| branch != "HEAD" && !branch.ends_with("/HEAD"))
         .collect();
 
-    branches.sort();
-    branches.dedup();
+    let recent = parser::checkouts(cmd, verbose)
+        .map_err(|()| "Failed to read checkout history from the HEAD reflog".to_string())?;
+
+    // The previous branch comes first and is therefore preselected
+    let branches = order_branches(branches, &recent);
 
     if branches.is_empty() {
-        let branch_type = if all {
-            "branches (local and remote)"
-        } else if remote {
-            "remote branches"
-        } else {
-            "local branches"
-        };
         return Err(format!("No {} found", branch_type));
     }
 
@@ -113,14 +425,122 @@ fn get_branches<T: Exec>(
     Ok(branch.to_string())
 }
 
+/// Orders branches by how recently they were checked out, followed by the date of their last
+/// commit for branches that don't appear in the checkout history
+fn order_branches(mut branches: Vec<(String, u64)>, recent: &[String]) -> Vec<String> {
+    let rank = |name: &str| {
+        recent
+            .iter()
+            .position(|branch| branch == name)
+            .unwrap_or(usize::MAX)
+    };
+
+    branches.sort_by(|(a, a_date), (b, b_date)| {
+        rank(a)
+            .cmp(&rank(b))
+            .then_with(|| b_date.cmp(a_date))
+            .then_with(|| a.cmp(b))
+    });
+
+    let mut result: Vec<String> = Vec::new();
+
+    for (name, _) in branches {
+        if !result.contains(&name) {
+            result.push(name);
+        }
+    }
+
+    result
+}
+
+fn list_recent<T: Exec>(cmd: &T, number: usize, verbose: bool) -> Result<String, String> {
+    let branches = parser::branches(cmd, &["refs/heads"], verbose)
+        .map_err(|()| "Failed to list local branches".to_string())?;
+
+    let checkouts = parser::checkouts(cmd, verbose)
+        .map_err(|()| "Failed to read checkout history from the HEAD reflog".to_string())?;
+
+    let mut listed: Vec<&str> = Vec::new();
+    let mut result = Vec::new();
+
+    for (index, name) in checkouts.iter().enumerate() {
+        if listed.len() == number {
+            break;
+        }
+
+        let exists = branches
+            .iter()
+            .any(|branch| !branch.head && branch.name() == name);
+
+        if !exists || listed.contains(&name.as_str()) {
+            continue;
+        }
+
+        listed.push(name);
+
+        // `@{-N}` can be passed back to `lgit checkout`
+        result.push(format!("@{{-{}}}  {name}", index + 1));
+    }
+
+    Ok(if result.is_empty() {
+        "No recently checked out branches".to_string()
+    } else {
+        result.join("\n")
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::commands::MockCmd;
 
+    fn cmd_status(status: &'static str) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| Ok(status.to_string()));
+
+        command
+    }
+
+    fn expect_current_branch(command: &mut MockCmd, branch: &'static str) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(move |_, _, _| Ok(format!("{branch}\n")));
+    }
+
+    fn expect_stash_sha(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "--quiet", "refs/stash"])
+            .times(2)
+            .returning({
+                // No stash before pushing one, the new stash after
+                let mut calls = 0;
+                move |_, _, _| {
+                    calls += 1;
+                    (calls > 1).then(|| "abc123\n".to_string()).ok_or(())
+                }
+            });
+    }
+
+    fn expect_stashes(command: &mut MockCmd, stashes: &'static str) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["stash", "list", "-z"]))
+            .times(1)
+            .returning(move |_, _, _| Ok(stashes.to_string()));
+    }
+
     #[test]
     fn test_run_with_specific_branch() {
-        let mut command = MockCmd::new();
+        let mut command = cmd_status("");
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
@@ -128,12 +548,13 @@ mod tests {
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
+        expect_current_branch(&mut command, "feature-branch");
+        expect_stashes(&mut command, "");
 
         let result = run(
             &command,
             Some("feature-branch".to_string()),
-            false,
-            false,
+            &Options::default(),
             false,
         );
 
@@ -142,7 +563,7 @@ mod tests {
 
     #[test]
     fn test_run_checkout_failure() {
-        let mut command = MockCmd::new();
+        let mut command = cmd_status("");
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
@@ -154,12 +575,364 @@ mod tests {
         let result = run(
             &command,
             Some("nonexistent-branch".to_string()),
+            &Options::default(),
             false,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run_autostash_parks_changes() {
+        let mut command = cmd_status(" M file.txt\n");
+        expect_current_branch(&mut command, "main");
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "stash",
+                    "push",
+                    "--include-untracked",
+                    "--message",
+                    "lgit-autostash:main",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_stash_sha(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_current_branch(&mut command, "feature");
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc123\x001760882700\0On main: lgit-autostash:main\0",
+        );
+
+        let options = Options {
+            autostash: true,
+            ..Options::default()
+        };
+
+        assert!(run(&command, Some("feature".to_string()), &options, false).is_ok());
+    }
+
+    #[test]
+    fn test_run_restores_parked_changes() {
+        let mut command = cmd_status("");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["status", "--porcelain"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_current_branch(&mut command, "feature");
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc123\x001760882700\0On main: lgit-autostash:main\0\
+             stash@{1}\0def456\x001760882700\0On feature: lgit-autostash:feature\0\
+             stash@{2}\0fed789\x001760882700\0On my-feature: lgit-autostash:my-feature\0",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{1}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = run(
+            &command,
+            Some("feature".to_string()),
+            &Options::default(),
             false,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_keeps_parked_changes_on_dirty_tree() {
+        let mut command = cmd_status(" M file.txt\n");
+        expect_current_branch(&mut command, "main");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.autostash"])
+            .times(1)
+            .returning(|_, _, _| Ok("false\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_current_branch(&mut command, "feature");
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc123\x001760882700\0On feature: lgit-autostash:feature\0",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["status", "--porcelain"])
+            .times(1)
+            .returning(|_, _, _| Ok(" M file.txt\n".to_string()));
+
+        let result = run(
+            &command,
+            Some("feature".to_string()),
+            &Options::default(),
             false,
         );
 
-        assert!(result.is_err());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_autostash_restored_when_checkout_fails() {
+        let mut command = cmd_status(" M file.txt\n");
+        expect_current_branch(&mut command, "main");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[..2] == ["stash", "push"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_stash_sha(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc123\x001760882700\0On main: lgit-stash:checkout 2026-10-19 14:05 UTC\0",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{0}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let options = Options {
+            autostash: true,
+            ..Options::default()
+        };
+
+        assert!(run(&command, Some("feature".to_string()), &options, false).is_err());
+    }
+
+    #[test]
+    fn test_run_carry_changes() {
+        let mut command = cmd_status(" M file.txt\n");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["stash", "push", "--include-untracked"]))
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_stash_sha(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc123\x001760882700\0On main: lgit-stash:checkout 2026-10-19 14:05 UTC\0",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{0}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let options = Options {
+            carry: true,
+            ..Options::default()
+        };
+
+        assert!(run(&command, Some("feature".to_string()), &options, false).is_ok());
+    }
+
+    #[test]
+    fn test_fetch_pull_request_from_github() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == [
+                    "fetch",
+                    "origin",
+                    "+refs/pull/42/head:refs/remotes/origin/pr/42",
+                ] && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/pr/42"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == ["branch", "--no-track", "pr/42", "refs/remotes/origin/pr/42"]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            fetch_pull_request(&command, 42, false),
+            Ok("pr/42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fetch_pull_request_falls_back_to_gitlab() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .times(1)
+            .returning(|_, _, _| Ok("upstream\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "fetch",
+                    "upstream",
+                    "+refs/pull/7/head:refs/remotes/upstream/pr/7",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "fetch",
+                    "upstream",
+                    "+refs/merge-requests/7/head:refs/remotes/upstream/pr/7",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert!(fetch_pull_request(&command, 7, false).is_err());
+    }
+
+    fn cmd_pull_request_branch(local_in_head: bool, head_in_local: bool) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/pr/7"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "merge-base",
+                    "--is-ancestor",
+                    "refs/remotes/origin/pr/7",
+                    "refs/heads/pr/7",
+                ]
+            })
+            .times(1)
+            .returning(move |_, _, _| head_in_local.then(String::new).ok_or(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "merge-base",
+                    "--is-ancestor",
+                    "refs/heads/pr/7",
+                    "refs/remotes/origin/pr/7",
+                ]
+            })
+            .returning(move |_, _, _| local_in_head.then(String::new).ok_or(()));
+
+        command
+    }
+
+    #[test]
+    fn test_update_pull_request_branch_fast_forwards_checked_out_branch() {
+        let mut command = cmd_pull_request_branch(true, false);
+        expect_current_branch(&mut command, "pr/7");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["merge", "--ff-only", "refs/remotes/origin/pr/7"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            update_pull_request_branch(&command, "pr/7", "refs/remotes/origin/pr/7", false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_update_pull_request_branch_fast_forwards_other_branch() {
+        let mut command = cmd_pull_request_branch(true, false);
+        expect_current_branch(&mut command, "main");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["branch", "--force", "pr/7", "refs/remotes/origin/pr/7"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            update_pull_request_branch(&command, "pr/7", "refs/remotes/origin/pr/7", false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_update_pull_request_branch_keeps_review_commits() {
+        let command = cmd_pull_request_branch(false, true);
+
+        assert_eq!(
+            update_pull_request_branch(&command, "pr/7", "refs/remotes/origin/pr/7", false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_update_pull_request_branch_keeps_diverged_branch() {
+        let command = cmd_pull_request_branch(false, false);
+
+        assert_eq!(
+            update_pull_request_branch(&command, "pr/7", "refs/remotes/origin/pr/7", false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_get_branches_no_pull_requests_found() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args[0] == "for-each-ref" && args[2..] == ["refs/heads/pr", "refs/remotes/*/pr/*"]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "reflog")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = get_branches(&command, false, false, true, false);
+
+        assert_eq!(result, Err("No pull request branches found".to_string()));
     }
 
     #[test]
@@ -203,64 +976,283 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["remote"] && !(*verbose) && !(*inherit_stderr)
+                args[0] == "for-each-ref"
+                    && args[2..] == ["refs/heads"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("origin".to_string()));
+            .returning(|_, _, _| Ok(String::new()));
 
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["branch", "--format", "%(refname)"] && !(*verbose) && !(*inherit_stderr)
+                args[0] == "reflog" && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        let result = get_branches(&command, false, false, false);
+        let result = get_branches(&command, false, false, false, false);
 
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "No local branches found");
     }
 
-    #[test]
-    fn test_get_branches_remote_failure() {
+    fn cmd_remote_branch(local_exists: bool) -> MockCmd {
         let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "show-ref",
+                    "--verify",
+                    "--quiet",
+                    "refs/heads/origin/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Err(()));
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
                 args == ["remote"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
+            .returning(|_, _, _| Ok("upstream\norigin\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "show-ref",
+                    "--verify",
+                    "--quiet",
+                    "refs/remotes/origin/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/feature"])
+            .times(1)
+            .returning(move |_, _, _| {
+                if local_exists {
+                    Ok(String::new())
+                } else {
+                    Err(())
+                }
+            });
+
+        command
+    }
+
+    #[test]
+    fn test_do_checkout_remote_creates_tracking_branch() {
+        let mut command = cmd_remote_branch(false);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "--track", "origin/feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = do_checkout(&command, "origin/feature", false);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_worktree_for_remote_branch() {
+        let mut command = cmd_remote_branch(false);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["worktree", "list", "--porcelain", "-z"])
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("worktree /src/repo\0HEAD abc123\0branch refs/heads/main\0\0".to_string())
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.worktreePath"])
+            .times(1)
             .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "worktree",
+                    "add",
+                    "--track",
+                    "-b",
+                    "feature",
+                    "/src/repo.worktrees/feature",
+                    "origin/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
 
-        let result = get_branches(&command, false, false, false);
+        let options = Options {
+            worktree: true,
+            ..Options::default()
+        };
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Failed to get git remotes (check network connection)");
+        let result = run(
+            &command,
+            Some("origin/feature".to_string()),
+            &options,
+            false,
+        );
+
+        assert!(result.is_ok());
     }
 
     #[test]
-    fn test_get_branches_branch_list_failure() {
-        let mut command = MockCmd::new();
+    fn test_do_checkout_remote_with_diverged_local_branch() {
+        let mut command = cmd_remote_branch(true);
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["remote"] && !(*verbose) && !(*inherit_stderr)
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/heads/feature...refs/remotes/origin/feature",
+                ]
             })
             .times(1)
-            .returning(|_, _, _| Ok("origin".to_string()));
+            .returning(|_, _, _| Ok("1\t2\n".to_string()));
+
+        let result = do_checkout(&command, "origin/feature", false);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_do_checkout_remote_failure() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "show-ref")
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["remote"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
 
+        let result = do_checkout(&command, "origin/feature", false);
+
+        assert_eq!(
+            result,
+            Err(Some(
+                "Failed to get git remotes (check network connection)".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_branches_branch_list_failure() {
+        let mut command = MockCmd::new();
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["branch", "--format", "%(refname)"] && !(*verbose) && !(*inherit_stderr)
+                args[0] == "for-each-ref"
+                    && args[2..] == ["refs/heads"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Err(()));
 
-        let result = get_branches(&command, false, false, false);
+        let result = get_branches(&command, false, false, false, false);
 
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Failed to list local branches");
     }
+
+    #[test]
+    fn test_order_branches_by_recent_checkouts_then_date() {
+        let branches = vec![
+            ("alpha".to_string(), 100),
+            ("beta".to_string(), 300),
+            ("gamma".to_string(), 200),
+            ("delta".to_string(), 400),
+            ("beta".to_string(), 300),
+        ];
+        let recent = vec![
+            "gamma".to_string(),
+            "alpha".to_string(),
+            "gamma".to_string(),
+        ];
+
+        assert_eq!(
+            order_branches(branches, &recent),
+            vec!["gamma", "alpha", "delta", "beta"]
+        );
+    }
+
+    #[test]
+    fn test_list_recent() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "for-each-ref" && args[2..] == ["refs/heads"])
+            .times(1)
+            .returning(|_, _, _| {
+                Ok([
+                    "*\0refs/heads/main\0\0\0\0\0",
+                    " \0refs/heads/feature\0\0\0\0\0",
+                    " \0refs/heads/fix\0\0\0\0\0",
+                ]
+                .join("\n"))
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["reflog", "show", "-z", "--format=%gs", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| {
+                Ok([
+                    "checkout: moving from feature to main",
+                    "checkout: moving from main to feature",
+                    "checkout: moving from deleted to main",
+                    "checkout: moving from fix to deleted",
+                    "checkout: moving from feature to fix",
+                ]
+                .join("\0"))
+            });
+
+        let result = list_recent(&command, 5, false);
+
+        assert_eq!(result, Ok("@{-1}  feature\n@{-4}  fix".to_string()));
+    }
+
+    #[test]
+    fn test_run_with_recent_limit() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "for-each-ref")
+            .times(1)
+            .returning(|_, _, _| Ok(" \0refs/heads/feature\0\0\0\0\0\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "reflog")
+            .times(1)
+            .returning(|_, _, _| Ok("checkout: moving from feature to main".to_string()));
+
+        let options = Options {
+            recent: Some(1),
+            ..Options::default()
+        };
+
+        let result = run(&command, None, &options, false);
+
+        assert!(result.is_ok());
+    }
 }
@@ -1,6 +1,7 @@
 use dialoguer::MultiSelect;
 
 use crate::commands::Exec;
+use crate::parser::{self, Commit};
 
 pub fn run(cmd: &dyn Exec, branch: &str, number: u32, verbose: bool) -> Result<(), Option<String>> {
     let commits = get_commits(cmd, branch, number, verbose)?;
@@ -12,27 +13,16 @@ pub fn run(cmd: &dyn Exec, branch: &str, number: u32, verbose: bool) -> Result<(
         .map_err(|err| format!("Failed to get user selection: {}", err))?;
 
     if selections.is_empty() {
-        return Err(Some(format!("No commits selected from {} available commits", commits.len())));
+        return Err(Some(format!(
+            "No commits selected from {} available commits",
+            commits.len()
+        )));
     }
 
-    let selected_commits: Result<Vec<&str>, String> = selections
+    let mut selected_commits: Vec<&str> = selections
         .iter()
-        .map(|&i| match commits[i].split_whitespace().next() {
-            Some(commit) => Ok(commit),
-            None => Err(format!("Invalid commit format: {}\n", commits[i])),
-        })
-        .collect::<Result<Vec<&str>, String>>();
-
-    let mut selected_commits = match selected_commits {
-        Ok(commits) => commits,
-        Err(err) => {
-            if verbose {
-                println!("{err}");
-            }
-
-            return Err(Some(format!("Failed to parse commit format: {}", err.trim())));
-        }
-    };
+        .map(|&i| commits[i].sha.as_str())
+        .collect();
 
     selected_commits.reverse();
 
@@ -50,21 +40,13 @@ fn get_commits(
     branch: &str,
     number: u32,
     verbose: bool,
-) -> Result<Vec<String>, String> {
-    let output = cmd
-        .exec(
-            &[
-                "log",
-                branch,
-                "--pretty=format:%h %s",
-                &format!("-n {number}"),
-            ],
-            verbose,
-            false,
+) -> Result<Vec<Commit>, String> {
+    parser::commits(cmd, &["-n", &number.to_string(), branch], verbose).map_err(|()| {
+        format!(
+            "Failed to get commit history from branch '{}' (last {} commits)",
+            branch, number
         )
-        .map_err(|()| format!("Failed to get commit history from branch '{}' (last {} commits)", branch, number))?;
-
-    Ok(output.lines().map(String::from).collect())
+    })
 }
 
 #[cfg(test)]
@@ -78,13 +60,22 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["log", "feature-branch", "--pretty=format:%h %s", "-n 5"]
-                    && !(*verbose)
+                args == [
+                    "log",
+                    "-z",
+                    "--format=%h%x00%s",
+                    "-n",
+                    "5",
+                    "feature-branch",
+                ] && !(*verbose)
                     && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| {
-                Ok("abc123 First commit\ndef456 Second commit\n789ghi Third commit".to_string())
+                Ok(
+                    "abc123\0First commit\0def456\0Second commit\0fed789\0Third commit\0"
+                        .to_string(),
+                )
             });
 
         let result = get_commits(&command, "feature-branch", 5, false);
@@ -92,9 +83,9 @@ mod tests {
         assert!(result.is_ok());
         let commits = result.unwrap();
         assert_eq!(commits.len(), 3);
-        assert_eq!(commits[0], "abc123 First commit");
-        assert_eq!(commits[1], "def456 Second commit");
-        assert_eq!(commits[2], "789ghi Third commit");
+        assert_eq!(commits[0].to_string(), "abc123 First commit");
+        assert_eq!(commits[1].sha, "def456");
+        assert_eq!(commits[2].subject, "Third commit");
     }
 
     #[test]
@@ -105,9 +96,11 @@ mod tests {
             .withf(|args, verbose, inherit_stderr| {
                 args == [
                     "log",
+                    "-z",
+                    "--format=%h%x00%s",
+                    "-n",
+                    "10",
                     "nonexistent-branch",
-                    "--pretty=format:%h %s",
-                    "-n 10",
                 ] && !(*verbose)
                     && !(*inherit_stderr)
             })
@@ -117,7 +110,10 @@ mod tests {
         let result = get_commits(&command, "nonexistent-branch", 10, false);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Failed to get commit history from branch 'nonexistent-branch' (last 10 commits)");
+        assert_eq!(
+            result.unwrap_err(),
+            "Failed to get commit history from branch 'nonexistent-branch' (last 10 commits)"
+        );
     }
 
     #[test]
@@ -126,7 +122,7 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["log", "empty-branch", "--pretty=format:%h %s", "-n 1"]
+                args == ["log", "-z", "--format=%h%x00%s", "-n", "1", "empty-branch"]
                     && !(*verbose)
                     && !(*inherit_stderr)
             })
@@ -137,7 +133,6 @@ mod tests {
 
         assert!(result.is_ok());
         let commits = result.unwrap();
-        // Empty string when split into lines becomes an empty vector
         assert_eq!(commits.len(), 0);
     }
 
@@ -147,12 +142,12 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["log", "test-branch", "--pretty=format:%h %s", "-n 3"]
+                args == ["log", "-z", "--format=%h%x00%s", "-n", "3", "test-branch"]
                     && *verbose
                     && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("abc123 Test commit".to_string()));
+            .returning(|_, _, _| Ok("abc123\0Test commit\0".to_string()));
 
         let result = get_commits(&command, "test-branch", 3, true);
 
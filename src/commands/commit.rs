@@ -0,0 +1,373 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, FuzzySelect, Input};
+
+use crate::commands::Exec;
+use crate::conventional::{self, Header, Rules};
+
+/// Directories holding the code of a project rather than naming a part of it
+const CONTAINER_DIRS: [&str; 8] = [
+    "src", "lib", "crates", "packages", "apps", "app", "pkg", "internal",
+];
+
+/// Commits the staged changes with `message`, or with a Conventional Commits message built from
+/// prompts when none is given. Either way the message has to pass the configured rules.
+pub fn run<T: Exec>(
+    command: &T,
+    message: Option<String>,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let rules = Rules::load(command, verbose);
+
+    let message = match message {
+        Some(message) => message,
+        None => {
+            let staged = staged_paths(command, verbose)?;
+
+            if staged.is_empty() {
+                return Err(Some(
+                    "Nothing staged to commit, stage changes with 'git add' first".to_string(),
+                ));
+            }
+
+            prompt_message(&rules, &suggest_scopes(&staged))?
+        }
+    };
+
+    check(&message, &rules)?;
+
+    // Inherited so that hooks can prompt and git reports the new commit
+    command
+        .exec(&["commit", "--message", &message], verbose, true)
+        .map_err(|()| "Failed to commit staged changes".to_string())?;
+
+    Ok(())
+}
+
+fn check(message: &str, rules: &Rules) -> Result<(), String> {
+    let problems = conventional::lint(message, rules);
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let problems: Vec<String> = problems
+        .iter()
+        .map(|problem| format!("  - {problem}"))
+        .collect();
+
+    Err(format!("Invalid commit message:\n{}", problems.join("\n")))
+}
+
+fn staged_paths<T: Exec>(command: &T, verbose: bool) -> Result<Vec<String>, String> {
+    let output = command
+        .exec(&["diff", "--cached", "--name-only", "-z"], verbose, false)
+        .map_err(|()| "Failed to list staged changes".to_string())?;
+
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string())
+        .collect())
+}
+
+/// Scopes for the changed paths, the one covering most paths first. A path names its scope with
+/// the first directory below containers such as `src`, or with its file name directly inside one,
+/// so `src/commands/push.rs` suggests `commands` and `src/parser.rs` suggests `parser`.
+fn suggest_scopes(paths: &[String]) -> Vec<String> {
+    let mut scopes: Vec<(String, usize)> = Vec::new();
+
+    for path in paths {
+        let mut parts: Vec<&str> = path.split('/').collect();
+        let contained = parts.len() > 1 && CONTAINER_DIRS.contains(&parts[0]);
+
+        while parts.len() > 1 && CONTAINER_DIRS.contains(&parts[0]) {
+            parts.remove(0);
+        }
+
+        // Files at the root, such as the README, belong to no part in particular
+        if parts.len() == 1 && !contained {
+            continue;
+        }
+
+        let scope = parts[0]
+            .rsplit_once('.')
+            .map_or(parts[0], |(stem, _)| stem)
+            .to_lowercase();
+
+        if scope.is_empty() {
+            continue;
+        }
+
+        match scopes.iter_mut().find(|(name, _)| *name == scope) {
+            Some((_, count)) => *count += 1,
+            None => scopes.push((scope, 1)),
+        }
+    }
+
+    // Stable, so scopes covering as many paths stay in the order they were found
+    scopes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+    scopes.into_iter().map(|(scope, _)| scope).collect()
+}
+
+fn prompt_message(rules: &Rules, scopes: &[String]) -> Result<String, String> {
+    let theme = ColorfulTheme::default();
+
+    let kind = FuzzySelect::with_theme(&theme)
+        .with_prompt("Type of change")
+        .default(0)
+        .items(&rules.types)
+        .interact()
+        .map_err(|err| format!("Failed to select type: {}", err))?;
+
+    let mut scope_prompt = Input::<String>::with_theme(&theme)
+        .with_prompt(match scopes.len() {
+            0 | 1 => "Scope (leave empty for none)".to_string(),
+            _ => format!("Scope (e.g. {}, leave empty for none)", scopes.join(", ")),
+        })
+        .allow_empty(true);
+
+    if let Some(scope) = scopes.first() {
+        scope_prompt = scope_prompt.with_initial_text(scope);
+    }
+
+    let scope = scope_prompt
+        .interact_text()
+        .map_err(|err| format!("Failed to read scope: {}", err))?;
+
+    let breaking = Confirm::with_theme(&theme)
+        .with_prompt("Is this a breaking change?")
+        .default(false)
+        .interact()
+        .map_err(|err| format!("Failed to confirm breaking change: {}", err))?;
+
+    let mut header = Header {
+        kind: rules.types[kind].clone(),
+        scope: Some(scope.trim().to_string()).filter(|scope| !scope.is_empty()),
+        breaking,
+        description: String::new(),
+    };
+
+    let description: String = Input::with_theme(&theme)
+        .with_prompt("Subject")
+        .validate_with(|description: &String| {
+            let candidate = Header {
+                kind: header.kind.clone(),
+                scope: header.scope.clone(),
+                breaking,
+                description: description.trim().to_string(),
+            };
+
+            match conventional::lint_subject(&candidate.to_string(), rules)
+                .into_iter()
+                .next()
+            {
+                Some(problem) => Err(problem),
+                None => Ok(()),
+            }
+        })
+        .interact_text()
+        .map_err(|err| format!("Failed to read subject: {}", err))?;
+
+    header.description = description.trim().to_string();
+
+    let body: String = Input::with_theme(&theme)
+        .with_prompt("Body (leave empty for none)")
+        .allow_empty(true)
+        .interact_text()
+        .map_err(|err| format!("Failed to read body: {}", err))?;
+
+    let breaking_change = if breaking {
+        Input::with_theme(&theme)
+            .with_prompt("Describe the breaking change")
+            .interact_text()
+            .map_err(|err| format!("Failed to read breaking change: {}", err))?
+    } else {
+        String::new()
+    };
+
+    let issues: String = Input::with_theme(&theme)
+        .with_prompt("Issues (e.g. #12, #34, leave empty for none)")
+        .allow_empty(true)
+        .interact_text()
+        .map_err(|err| format!("Failed to read issues: {}", err))?;
+
+    Ok(build(
+        &header,
+        &body,
+        &breaking_change,
+        &issues,
+        rules.body_max_length,
+    ))
+}
+
+/// Puts the message together, wrapping the body and adding `BREAKING CHANGE` and `Refs` footers
+fn build(header: &Header, body: &str, breaking_change: &str, issues: &str, width: usize) -> String {
+    let mut message = header.to_string();
+
+    if !body.trim().is_empty() {
+        message.push_str(&format!("\n\n{}", wrap(body.trim(), width)));
+    }
+
+    let mut footers = Vec::new();
+
+    if !breaking_change.trim().is_empty() {
+        footers.push(wrap(
+            &format!("BREAKING CHANGE: {}", breaking_change.trim()),
+            width,
+        ));
+    }
+
+    let issues: Vec<String> = issues
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|issue| !issue.is_empty())
+        .map(|issue| match issue.parse::<u64>() {
+            Ok(_) => format!("#{issue}"),
+            Err(_) => issue.to_string(),
+        })
+        .collect();
+
+    if !issues.is_empty() {
+        footers.push(format!("Refs: {}", issues.join(", ")));
+    }
+
+    if !footers.is_empty() {
+        message.push_str(&format!("\n\n{}", footers.join("\n")));
+    }
+
+    message
+}
+
+/// Wraps `text` at word boundaries so that no line is longer than `width`, unless a single word is
+fn wrap(text: &str, width: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+
+        line.push_str(word);
+    }
+
+    lines.push(line);
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn expect_rules(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "config")
+            .times(7)
+            .returning(|_, _, _| Err(()));
+    }
+
+    #[test]
+    fn test_suggest_scopes() {
+        let paths: Vec<String> = [
+            "src/commands/push.rs",
+            "src/parser.rs",
+            "src/commands/land.rs",
+            "README.md",
+            "docs/usage.md",
+            "crates/core/src/lib.rs",
+        ]
+        .iter()
+        .map(|path| path.to_string())
+        .collect();
+
+        assert_eq!(
+            suggest_scopes(&paths),
+            ["commands", "parser", "docs", "core"]
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        let header = Header::parse("feat(cli)!: add a commit command").unwrap();
+
+        assert_eq!(
+            build(
+                &header,
+                "Builds the message from prompts so that it follows the rules",
+                "the commit alias is gone",
+                "12, #34 ABC-5",
+                30
+            ),
+            "feat(cli)!: add a commit command\n\n\
+             Builds the message from\n\
+             prompts so that it follows the\n\
+             rules\n\n\
+             BREAKING CHANGE: the commit\n\
+             alias is gone\n\
+             Refs: #12, #34, ABC-5"
+        );
+    }
+
+    #[test]
+    fn test_build_subject_only() {
+        let header = Header::parse("fix: handle empty input").unwrap();
+
+        assert_eq!(build(&header, " ", "", "", 100), "fix: handle empty input");
+    }
+
+    #[test]
+    fn test_run_with_message() {
+        let mut command = MockCmd::new();
+        expect_rules(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["commit", "--message", "fix: handle empty input"]
+                    && !(*verbose)
+                    && *inherit_stderr
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            run(&command, Some("fix: handle empty input".to_string()), false),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_run_rejects_invalid_message() {
+        let mut command = MockCmd::new();
+        expect_rules(&mut command);
+
+        assert_eq!(
+            run(&command, Some("Fixed stuff".to_string()), false),
+            Err(Some("Invalid commit message:\n  - The subject 'Fixed stuff' doesn't follow Conventional Commits, e.g. 'feat(scope): add something'".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_run_without_staged_changes() {
+        let mut command = MockCmd::new();
+        expect_rules(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["diff", "--cached", "--name-only", "-z"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            run(&command, None, false),
+            Err(Some(
+                "Nothing staged to commit, stage changes with 'git add' first".to_string()
+            ))
+        );
+    }
+}
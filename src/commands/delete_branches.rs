@@ -1,4 +1,33 @@
-use crate::commands::Exec;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Confirm;
+
+use crate::commands::{trash, Exec};
+use crate::parser;
+use crate::utils::{
+    ahead_behind, days_to_seconds, matches_protected, now, parse_days, protected_patterns,
+};
+
+/// Number of branches removed per `git push --delete` invocation
+const PUSH_BATCH_SIZE: usize = 50;
+
+#[derive(Debug, PartialEq)]
+struct StaleBranch {
+    remote: Option<String>,
+    name: String,
+    age_days: u64,
+    author: String,
+    ahead: u32,
+    behind: u32,
+}
+
+impl StaleBranch {
+    fn display_name(&self) -> String {
+        match &self.remote {
+            Some(remote) => format!("{remote}/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+}
 
 pub fn run<T: Exec>(command: &T, dry_run: bool, verbose: bool) -> Result<(), Option<String>> {
     match delete_branches(command, dry_run, verbose) {
@@ -13,25 +42,25 @@ pub fn run<T: Exec>(command: &T, dry_run: bool, verbose: bool) -> Result<(), Opt
 fn delete_branches<T: Exec>(command: &T, dry_run: bool, verbose: bool) -> Result<String, String> {
     command
         .exec(&["fetch", "--prune"], verbose, false)
-        .map_err(|()| "Failed to fetch and prune from remote (check network connection)".to_string())?;
+        .map_err(|()| {
+            "Failed to fetch and prune from remote (check network connection)".to_string()
+        })?;
 
-    let branches = command
-        .exec(&["branch", "-vv"], verbose, false)
+    let branches = parser::branches(command, &["refs/heads"], verbose)
         .map_err(|()| "Failed to get branch information with tracking details".to_string())?;
 
     let mut result = Vec::new();
 
-    for line in branches.lines() {
-        if line.starts_with('*') || !line.contains(": gone]") {
+    for branch in &branches {
+        if branch.head || !branch.track.gone {
             continue;
         }
 
-        let branch_name = line
-            .split_whitespace()
-            .next()
-            .ok_or_else(|| format!("Failed to parse branch name from line: '{}'", line.trim()))?;
+        let branch_name = branch.name();
 
         if !dry_run {
+            trash::backup(command, branch_name, verbose)?;
+
             command
                 .exec(&["branch", "-D", branch_name], verbose, false)
                 .map_err(|()| format!("Failed to delete branch '{}'", branch_name))?;
@@ -47,9 +76,185 @@ fn delete_branches<T: Exec>(command: &T, dry_run: bool, verbose: bool) -> Result
     })
 }
 
+pub fn run_stale<T: Exec>(
+    command: &T,
+    age: &str,
+    author: Option<&str>,
+    base: &str,
+    dry_run: bool,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let days = parse_days(age)?;
+
+    command
+        .exec(&["fetch", "--prune"], verbose, false)
+        .map_err(|()| {
+            "Failed to fetch and prune from remote (check network connection)".to_string()
+        })?;
+
+    let branches = find_stale(command, days, author, base, verbose)?;
+
+    if branches.is_empty() {
+        println!("No branches older than {age}");
+        return Ok(());
+    }
+
+    println!("{}", format_report(&branches, base));
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Delete {} stale branches?", branches.len()))
+        .default(false)
+        .interact()
+        .map_err(|err| format!("Failed to confirm deletion: {}", err))?;
+
+    if !confirmed {
+        return Err(Some("Aborted, no branches were deleted".to_string()));
+    }
+
+    println!("{}", delete_stale(command, &branches, verbose)?);
+
+    Ok(())
+}
+
+fn find_stale<T: Exec>(
+    command: &T,
+    days: u64,
+    author: Option<&str>,
+    base: &str,
+    verbose: bool,
+) -> Result<Vec<StaleBranch>, String> {
+    let branches = parser::branches(command, &["refs/heads", "refs/remotes"], verbose)
+        .map_err(|()| "Failed to list branches".to_string())?;
+
+    let cutoff = now().saturating_sub(days_to_seconds(days));
+    let author = author.map(str::to_lowercase);
+    let protected = protected_patterns(command, verbose);
+    let mut result = Vec::new();
+
+    for branch in &branches {
+        let refname = branch.refname.as_str();
+
+        let (remote, name) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+            (None, name)
+        } else if let Some((remote, name)) = branch.name().split_once('/') {
+            (Some(remote.to_string()), name)
+        } else {
+            continue;
+        };
+
+        if branch.head || name == "HEAD" || name == base || matches_protected(name, &protected) {
+            continue;
+        }
+
+        // A branch without a known date isn't known to be stale
+        let Some(committer_date) = branch.committer_date else {
+            continue;
+        };
+
+        if committer_date >= cutoff {
+            continue;
+        }
+
+        if let Some(author) = &author {
+            if !branch.author.to_lowercase().contains(author) {
+                continue;
+            }
+        }
+
+        let (ahead, behind) = ahead_behind(command, refname, base, verbose)
+            .map_err(|()| format!("Failed to compare '{}' with '{}'", refname, base))?;
+
+        result.push(StaleBranch {
+            ahead,
+            behind,
+            remote,
+            name: name.to_string(),
+            age_days: (now().saturating_sub(committer_date)) / days_to_seconds(1),
+            author: branch.author.clone(),
+        });
+    }
+
+    Ok(result)
+}
+
+fn format_report(branches: &[StaleBranch], base: &str) -> String {
+    let width = branches
+        .iter()
+        .map(|branch| branch.display_name().len())
+        .max()
+        .unwrap_or(0);
+
+    branches
+        .iter()
+        .map(|branch| {
+            format!(
+                "{:width$}  {:>5} days  +{}/-{} vs {base}  {}",
+                branch.display_name(),
+                branch.age_days,
+                branch.ahead,
+                branch.behind,
+                branch.author,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn delete_stale<T: Exec>(
+    command: &T,
+    branches: &[StaleBranch],
+    verbose: bool,
+) -> Result<String, String> {
+    let mut result = Vec::new();
+    let mut remotes: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for branch in branches {
+        match &branch.remote {
+            Some(remote) => match remotes.iter_mut().find(|(name, _)| name == remote) {
+                Some((_, names)) => names.push(&branch.name),
+                None => remotes.push((remote, vec![&branch.name])),
+            },
+            None => {
+                trash::backup(command, &branch.name, verbose)?;
+
+                command
+                    .exec(&["branch", "-D", &branch.name], verbose, false)
+                    .map_err(|()| format!("Failed to delete branch '{}'", branch.name))?;
+
+                result.push(format!("Deleted branch {}", branch.name));
+            }
+        }
+    }
+
+    for (remote, names) in remotes {
+        for batch in names.chunks(PUSH_BATCH_SIZE) {
+            let mut args = vec!["push", remote, "--delete"];
+            args.extend(batch);
+
+            command
+                .exec(&args, verbose, false)
+                .map_err(|()| format!("Failed to delete branches from remote '{}'", remote))?;
+
+            result.extend(
+                batch
+                    .iter()
+                    .map(|name| format!("Deleted remote branch {remote}/{name}")),
+            );
+        }
+    }
+
+    Ok(result.join("\n"))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::commands::delete_branches::delete_branches;
+    use crate::commands::delete_branches::{
+        delete_branches, delete_stale, find_stale, format_report, StaleBranch,
+    };
     use crate::commands::MockCmd;
 
     fn cmd_fetch_prune() -> MockCmd {
@@ -65,13 +270,52 @@ mod tests {
         command
     }
 
+    fn expect_backup(command: &mut MockCmd, branch: &'static str) {
+        command
+            .expect_exec()
+            .withf(move |args, _, _| {
+                args == ["rev-parse", "--verify", &format!("refs/heads/{branch}")]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| {
+                args[0] == "update-ref" && args[1].ends_with(&format!("/{branch}"))
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| {
+                args[0] == "config"
+                    && args[1] == "--get"
+                    && args[2].starts_with(&format!("branch.{branch}."))
+            })
+            .times(2)
+            .returning(|_, _, _| Err(()));
+    }
+
     fn cmd_fetch_prune_branch() -> MockCmd {
         let mut command = cmd_fetch_prune();
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| args == ["branch", "-vv"] && !(*verbose) && !(*inherit_stderr))
+            .withf(|args, verbose, inherit_stderr| {
+                args[0] == "for-each-ref"
+                    && args[2..] == ["refs/heads"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
             .times(1)
-            .returning(|_, _, _| Ok("  branch1 [origin/branch1: gone]\n  branch2 [origin/branch2: gone]\n* branch3 [origin/branch3]".to_string()));
+            .returning(|_, _, _| {
+                Ok([
+                    " \0refs/heads/branch1\0refs/remotes/origin/branch1\0[gone]\0\0\0",
+                    " \0refs/heads/branch2\0refs/remotes/origin/branch2\0[gone]\0\0\0",
+                    "*\0refs/heads/branch3\0refs/remotes/origin/branch3\0\0\0\0",
+                    " \0refs/heads/branch4\0refs/remotes/origin/branch4\0[ahead 1]\0\0\0",
+                ]
+                .join("\n"))
+            });
 
         command
     }
@@ -92,6 +336,8 @@ mod tests {
     #[test]
     fn delete_branches_does_not_delete_current_branch() {
         let mut command = cmd_fetch_prune_branch();
+        expect_backup(&mut command, "branch1");
+        expect_backup(&mut command, "branch2");
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
@@ -119,6 +365,7 @@ mod tests {
     #[test]
     fn delete_branches_returns_error_when_delete_fails() {
         let mut command = cmd_fetch_prune_branch();
+        expect_backup(&mut command, "branch1");
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
@@ -138,14 +385,182 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["branch", "-vv"] && !(*verbose) && !(*inherit_stderr)
+                args[0] == "for-each-ref"
+                    && args[2..] == ["refs/heads"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("* branch3 [origin/branch3]".to_string()));
+            .returning(|_, _, _| {
+                Ok("*\0refs/heads/branch3\0refs/remotes/origin/branch3\0\0\0\0\n".to_string())
+            });
 
         let result = delete_branches(&command, false, false);
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "No branches to delete");
     }
+
+    #[test]
+    fn delete_branches_does_not_delete_when_backup_fails() {
+        let mut command = cmd_fetch_prune_branch();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "refs/heads/branch1"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let result = delete_branches(&command, false, false);
+
+        assert!(result.is_err());
+    }
+
+    fn cmd_stale_refs() -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args[0] == "for-each-ref"
+                    && args[2..] == ["refs/heads", "refs/remotes"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok([
+                    "*\0refs/heads/current\0\0\x001\0Jane <jane@example.com>\0",
+                    " \0refs/heads/main\0\0\x001\0Jane <jane@example.com>\0",
+                    " \0refs/heads/old\0\0\x001\0Jane <jane@example.com>\0",
+                    " \0refs/heads/fresh\0\0\x0099999999999\0Jane <jane@example.com>\0",
+                    " \0refs/remotes/origin/HEAD\0\0\x001\0Jane <jane@example.com>\0",
+                    " \0refs/remotes/origin/abandoned\0\0\x001\0John <john@example.com>\0",
+                    " \0refs/remotes/origin/release/1.0\0\0\x001\0John <john@example.com>\0",
+                    " \0refs/remotes/origin/undated\0\0\0\0John <john@example.com>\0",
+                ]
+                .join("\n"))
+            });
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.protected"])
+            .times(1)
+            .returning(|_, _, _| Ok("main,release/*\n".to_string()));
+
+        command
+    }
+
+    #[test]
+    fn find_stale_skips_current_base_protected_undated_and_recent_branches() {
+        let mut command = cmd_stale_refs();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/heads/old...main",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("2\t4\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/remotes/origin/abandoned...main",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("0\t10\n".to_string()));
+
+        let result = find_stale(&command, 90, None, "main", false).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].display_name(), "old");
+        assert_eq!((result[0].ahead, result[0].behind), (2, 4));
+        assert_eq!(result[1].display_name(), "origin/abandoned");
+        assert_eq!((result[1].ahead, result[1].behind), (0, 10));
+    }
+
+    #[test]
+    fn find_stale_filters_by_author() {
+        let mut command = cmd_stale_refs();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "rev-list",
+                    "--left-right",
+                    "--count",
+                    "refs/remotes/origin/abandoned...main",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("0\t10\n".to_string()));
+
+        let result = find_stale(&command, 90, Some("JOHN@"), "main", false).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].author, "John <john@example.com>");
+    }
+
+    #[test]
+    fn format_report_aligns_branch_names() {
+        let branches = vec![
+            StaleBranch {
+                remote: None,
+                name: "old".to_string(),
+                age_days: 120,
+                author: "Jane <jane@example.com>".to_string(),
+                ahead: 2,
+                behind: 4,
+            },
+            StaleBranch {
+                remote: Some("origin".to_string()),
+                name: "abandoned".to_string(),
+                age_days: 365,
+                author: "John <john@example.com>".to_string(),
+                ahead: 0,
+                behind: 10,
+            },
+        ];
+
+        assert_eq!(
+            format_report(&branches, "main"),
+            "old                 120 days  +2/-4 vs main  Jane <jane@example.com>\n\
+             origin/abandoned    365 days  +0/-10 vs main  John <john@example.com>"
+        );
+    }
+
+    #[test]
+    fn delete_stale_batches_remote_branches() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["push", "origin", "--delete", "a", "b"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let branches: Vec<StaleBranch> = ["a", "b"]
+            .iter()
+            .map(|name| StaleBranch {
+                remote: Some("origin".to_string()),
+                name: name.to_string(),
+                age_days: 100,
+                author: String::new(),
+                ahead: 0,
+                behind: 0,
+            })
+            .collect();
+
+        let result = delete_stale(&command, &branches, false);
+
+        assert_eq!(
+            result,
+            Ok("Deleted remote branch origin/a\nDeleted remote branch origin/b".to_string())
+        );
+    }
 }
@@ -0,0 +1,174 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::HooksAction;
+use crate::commands::Exec;
+
+/// First line after the shebang of every hook lgit writes, telling them apart from the user's own
+const MARKER: &str = "# Installed by 'lgit hooks install'";
+
+/// Hooks and the lgit command each of them runs
+const HOOKS: [(&str, &str); 2] = [
+    ("commit-msg", "lgit lint-msg \"$1\""),
+    ("pre-push", "lgit lint-branch --pre-push"),
+];
+
+pub fn run<T: Exec>(command: &T, action: HooksAction, verbose: bool) -> Result<(), Option<String>> {
+    match action {
+        HooksAction::Install => {
+            // Resolved by git, which honors `core.hooksPath`
+            let dir = command
+                .exec(&["rev-parse", "--git-path", "hooks"], verbose, false)
+                .map_err(|()| "Failed to find the hooks directory".to_string())?;
+
+            let mut installed = Vec::new();
+
+            for (hook, lgit) in HOOKS {
+                installed.push(install(Path::new(dir.trim()), hook, lgit)?);
+            }
+
+            println!("{}", installed.join("\n"));
+
+            Ok(())
+        }
+    }
+}
+
+/// Writes the `hook` script running `lgit`. A hook the user wrote is kept as `<hook>.pre-lgit`
+/// and still runs first, so installing never loses what the repository already checks.
+fn install(dir: &Path, hook: &str, lgit: &str) -> Result<String, String> {
+    let path = dir.join(hook);
+    let script = script(hook, lgit);
+    let mut kept = false;
+
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if existing == script {
+            return Ok(format!("The {hook} hook is already installed"));
+        }
+
+        if !existing.contains(MARKER) {
+            let previous = dir.join(format!("{hook}.pre-lgit"));
+
+            if previous.exists() {
+                return Err(format!(
+                    "Failed to install the {} hook, both '{}' and '{}' exist",
+                    hook,
+                    path.display(),
+                    previous.display()
+                ));
+            }
+
+            fs::rename(&path, &previous)
+                .map_err(|err| format!("Failed to keep the existing {} hook: {}", hook, err))?;
+
+            kept = true;
+        }
+    }
+
+    fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&path, script))
+        .map_err(|err| format!("Failed to write '{}': {}", path.display(), err))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("Failed to make '{}' executable: {}", path.display(), err))?;
+    }
+
+    Ok(match kept {
+        true => {
+            format!("Installed the {hook} hook, the existing one runs first as {hook}.pre-lgit")
+        }
+        false => format!("Installed the {hook} hook"),
+    })
+}
+
+/// The script of `hook`, which keeps what git passes on stdin (the pushed refs for `pre-push`) for
+/// both the kept hook and lgit
+fn script(hook: &str, lgit: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {MARKER}\n\
+         hooks=$(dirname \"$0\")\n\
+         input=$(cat)\n\
+         \n\
+         if [ -x \"$hooks/{hook}.pre-lgit\" ]; then\n\
+         \x20   printf '%s\\n' \"$input\" | \"$hooks/{hook}.pre-lgit\" \"$@\" || exit $?\n\
+         fi\n\
+         \n\
+         printf '%s\\n' \"$input\" | exec {lgit}\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_hooks(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("lgit-hooks-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn test_script() {
+        assert_eq!(
+            script("pre-push", "lgit lint-branch --pre-push"),
+            "#!/bin/sh\n\
+             # Installed by 'lgit hooks install'\n\
+             hooks=$(dirname \"$0\")\n\
+             input=$(cat)\n\
+             \n\
+             if [ -x \"$hooks/pre-push.pre-lgit\" ]; then\n\
+             \x20   printf '%s\\n' \"$input\" | \"$hooks/pre-push.pre-lgit\" \"$@\" || exit $?\n\
+             fi\n\
+             \n\
+             printf '%s\\n' \"$input\" | exec lgit lint-branch --pre-push\n"
+        );
+    }
+
+    #[test]
+    fn test_install_keeps_existing_hook() {
+        let dir = temp_hooks("existing");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("commit-msg"), "#!/bin/sh\nexit 0\n").unwrap();
+
+        let installed = install(&dir, "commit-msg", "lgit lint-msg \"$1\"");
+        let again = install(&dir, "commit-msg", "lgit lint-msg \"$1\"");
+        let previous = fs::read_to_string(dir.join("commit-msg.pre-lgit"));
+        let hook = fs::read_to_string(dir.join("commit-msg"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            installed,
+            Ok(
+                "Installed the commit-msg hook, the existing one runs first as commit-msg.pre-lgit"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            again,
+            Ok("The commit-msg hook is already installed".to_string())
+        );
+        assert_eq!(previous.unwrap(), "#!/bin/sh\nexit 0\n");
+        assert!(hook.unwrap().contains(MARKER));
+    }
+
+    #[test]
+    fn test_install_refuses_to_overwrite_kept_hook() {
+        let dir = temp_hooks("kept");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pre-push"), "#!/bin/sh\n").unwrap();
+        fs::write(dir.join("pre-push.pre-lgit"), "#!/bin/sh\n").unwrap();
+
+        let installed = install(&dir, "pre-push", "lgit lint-branch --pre-push");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(installed
+            .unwrap_err()
+            .starts_with("Failed to install the pre-push hook"));
+    }
+}
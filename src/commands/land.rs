@@ -0,0 +1,498 @@
+use crate::commands::{trash, Exec};
+use crate::config;
+use crate::parser;
+use crate::utils::{get_current_branch, get_remote, is_protected, refresh_base};
+
+/// Tips of the landed branch and its base before landing, restored when landing fails
+struct State {
+    branch: String,
+    branch_sha: String,
+    base: String,
+    base_sha: String,
+}
+
+/// Lands the current branch on `base`: folds in pending fixups, rebases onto the refreshed base,
+/// fast-forwards (or squash-merges) the base to it, pushes the base and deletes the branch
+pub fn run<T: Exec>(
+    command: &T,
+    base: &str,
+    squash: bool,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let branch = get_current_branch(command, verbose)
+        .ok_or_else(|| "Failed to land, HEAD is not on a branch".to_string())?;
+
+    if branch == base || is_protected(command, &branch, verbose) {
+        return Err(Some(format!(
+            "Refusing to land protected branch '{}'",
+            branch
+        )));
+    }
+
+    let status = command
+        .exec(&["status", "--porcelain"], verbose, false)
+        .map_err(|()| "Failed to retrieve git status (check if in git repository)".to_string())?;
+
+    if !status.is_empty() {
+        return Err(Some(format!(
+            "Failed to land '{}', commit or stash uncommitted changes first",
+            branch
+        )));
+    }
+
+    let state = State {
+        branch_sha: resolve(command, &branch, verbose)?,
+        branch,
+        base_sha: resolve(command, base, verbose)?,
+        base: base.to_string(),
+    };
+
+    if let Err(err) = land(command, &state, squash, verbose) {
+        restore(command, &state, verbose);
+
+        return Err(Some(format!(
+            "{err}, restored '{}' and '{}'",
+            state.branch, state.base
+        )));
+    }
+
+    delete_branch(command, &state.branch, verbose)?;
+
+    println!("Landed {} on {}", state.branch, state.base);
+
+    Ok(())
+}
+
+fn resolve<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<String, String> {
+    command
+        .exec(
+            &["rev-parse", "--verify", &format!("refs/heads/{branch}")],
+            verbose,
+            false,
+        )
+        .map(|sha| sha.trim().to_string())
+        .map_err(|()| format!("Failed to resolve branch '{}'", branch))
+}
+
+fn land<T: Exec>(command: &T, state: &State, squash: bool, verbose: bool) -> Result<(), String> {
+    let (branch, base) = (state.branch.as_str(), state.base.as_str());
+
+    let upstream = refresh_base(command, base, verbose)
+        .map_err(|()| format!("Failed to refresh base branch '{}'", base))?;
+
+    // Folds in fixups and rebases onto the refreshed base in one go
+    command
+        .exec(
+            &[
+                "-c",
+                "sequence.editor=:",
+                "rebase",
+                "--interactive",
+                "--autosquash",
+                &upstream,
+            ],
+            verbose,
+            false,
+        )
+        .map_err(|()| format!("Failed to rebase '{}' onto '{}'", branch, upstream))?;
+
+    let message = if squash {
+        Some(squash_message(command, &upstream, branch, verbose)?)
+    } else {
+        None
+    };
+
+    command
+        .exec(&["checkout", base], verbose, false)
+        .map_err(|()| format!("Failed to checkout base branch '{}'", base))?;
+
+    command
+        .exec(&["merge", "--ff-only", &upstream], verbose, false)
+        .map_err(|()| {
+            format!(
+                "Failed to fast-forward '{}', it has diverged from '{}'",
+                base, upstream
+            )
+        })?;
+
+    match message {
+        Some(message) => {
+            command
+                .exec(&["merge", "--squash", branch], verbose, false)
+                .map_err(|()| format!("Failed to squash-merge '{}' into '{}'", branch, base))?;
+
+            command
+                .exec(&["commit", "--message", &message], verbose, false)
+                .map_err(|()| format!("Failed to commit squashed '{}'", branch))?;
+        }
+        None => {
+            command
+                .exec(&["merge", "--ff-only", branch], verbose, false)
+                .map_err(|()| format!("Failed to fast-forward '{}' to '{}'", base, branch))?;
+        }
+    }
+
+    let remote = get_remote(command, verbose);
+
+    command
+        .exec(&["push", &remote, base], verbose, true)
+        .map_err(|()| format!("Failed to push '{}' to '{}'", base, remote))?;
+
+    Ok(())
+}
+
+/// Message of the squashed commit: the subject of the first commit, followed by a list of all of
+/// them when there are several
+fn squash_message<T: Exec>(
+    command: &T,
+    upstream: &str,
+    branch: &str,
+    verbose: bool,
+) -> Result<String, String> {
+    let commits = parser::commits(
+        command,
+        &["--reverse", &format!("{upstream}..{branch}")],
+        verbose,
+    )
+    .map_err(|()| format!("Failed to list commits of '{}'", branch))?;
+
+    let Some(first) = commits.first() else {
+        return Err(format!("Branch '{}' has no commits to land", branch));
+    };
+
+    if commits.len() == 1 {
+        return Ok(first.subject.clone());
+    }
+
+    let list: Vec<String> = commits
+        .iter()
+        .map(|commit| format!("* {}", commit.subject))
+        .collect();
+
+    Ok(format!("{}\n\n{}", first.subject, list.join("\n")))
+}
+
+/// Puts both branches back where they were, whichever step landing failed at
+fn restore<T: Exec>(command: &T, state: &State, verbose: bool) {
+    // Each step is best effort, as the earlier ones may not have happened
+    let _ = command.exec(&["rebase", "--abort"], verbose, false);
+    let _ = command.exec(&["checkout", "--force", &state.branch], verbose, false);
+    let _ = command.exec(
+        &[
+            "update-ref",
+            &format!("refs/heads/{}", state.base),
+            &state.base_sha,
+        ],
+        verbose,
+        false,
+    );
+    let _ = command.exec(&["reset", "--hard", &state.branch_sha], verbose, false);
+}
+
+/// Deletes the landed branch on its remote and locally, keeping a backup in the trash
+fn delete_branch<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<(), String> {
+    let remote = config::get(command, &format!("branch.{branch}.remote"), verbose)
+        .filter(|remote| remote != ".");
+    let merge = config::get(command, &format!("branch.{branch}.merge"), verbose);
+
+    if let (Some(remote), Some(merge)) = (remote, merge) {
+        let remote_branch = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+
+        command
+            .exec(
+                &["push", &remote, "--delete", remote_branch],
+                verbose,
+                false,
+            )
+            .map_err(|()| {
+                format!(
+                    "Landed '{}', but failed to delete '{}/{}'",
+                    branch, remote, remote_branch
+                )
+            })?;
+    }
+
+    trash::backup(command, branch, verbose)?;
+
+    command
+        .exec(&["branch", "-D", branch], verbose, false)
+        .map_err(|()| format!("Landed '{}', but failed to delete it", branch))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn state() -> State {
+        State {
+            branch: "feature".to_string(),
+            branch_sha: "aaa111".to_string(),
+            base: "main".to_string(),
+            base_sha: "bbb222".to_string(),
+        }
+    }
+
+    fn expect(
+        command: &mut MockCmd,
+        expected: &'static [&'static str],
+        result: Result<&'static str, ()>,
+    ) {
+        command
+            .expect_exec()
+            .withf(move |args, verbose, inherit_stderr| {
+                args == expected && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| result.map(str::to_string));
+    }
+
+    fn expect_refresh_base(command: &mut MockCmd) {
+        expect(
+            command,
+            &["config", "--type=bool", "--get", "lgit.fastForwardBase"],
+            Err(()),
+        );
+        expect(command, &["config", "--get", "lgit.remote"], Err(()));
+        expect(command, &["fetch", "origin", "main:main"], Ok(""));
+        expect(
+            command,
+            &[
+                "show-ref",
+                "--verify",
+                "--quiet",
+                "refs/remotes/origin/main",
+            ],
+            Ok(""),
+        );
+    }
+
+    fn expect_restore(command: &mut MockCmd) {
+        expect(command, &["rebase", "--abort"], Err(()));
+        expect(command, &["checkout", "--force", "feature"], Ok(""));
+        expect(
+            command,
+            &["update-ref", "refs/heads/main", "bbb222"],
+            Ok(""),
+        );
+        expect(command, &["reset", "--hard", "aaa111"], Ok(""));
+    }
+
+    #[test]
+    fn test_land_fast_forward() {
+        let mut command = MockCmd::new();
+        expect_refresh_base(&mut command);
+        expect(
+            &mut command,
+            &[
+                "-c",
+                "sequence.editor=:",
+                "rebase",
+                "--interactive",
+                "--autosquash",
+                "origin/main",
+            ],
+            Ok(""),
+        );
+        expect(&mut command, &["checkout", "main"], Ok(""));
+        expect(&mut command, &["merge", "--ff-only", "origin/main"], Ok(""));
+        expect(&mut command, &["merge", "--ff-only", "feature"], Ok(""));
+        expect(&mut command, &["config", "--get", "lgit.remote"], Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, inherit_stdio| args == ["push", "origin", "main"] && *inherit_stdio)
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(land(&command, &state(), false, false), Ok(()));
+    }
+
+    #[test]
+    fn test_land_squash() {
+        let mut command = MockCmd::new();
+        expect_refresh_base(&mut command);
+        expect(
+            &mut command,
+            &[
+                "-c",
+                "sequence.editor=:",
+                "rebase",
+                "--interactive",
+                "--autosquash",
+                "origin/main",
+            ],
+            Ok(""),
+        );
+        expect(
+            &mut command,
+            &[
+                "log",
+                "-z",
+                "--format=%h%x00%s",
+                "--reverse",
+                "origin/main..feature",
+            ],
+            Ok("abc123\0feat: add login\0def456\0fix: typo\0"),
+        );
+        expect(&mut command, &["checkout", "main"], Ok(""));
+        expect(&mut command, &["merge", "--ff-only", "origin/main"], Ok(""));
+        expect(&mut command, &["merge", "--squash", "feature"], Ok(""));
+        expect(
+            &mut command,
+            &[
+                "commit",
+                "--message",
+                "feat: add login\n\n* feat: add login\n* fix: typo",
+            ],
+            Ok(""),
+        );
+        expect(&mut command, &["config", "--get", "lgit.remote"], Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, inherit_stdio| args == ["push", "origin", "main"] && *inherit_stdio)
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(land(&command, &state(), true, false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_restores_state_when_push_fails() {
+        let mut command = MockCmd::new();
+        expect(
+            &mut command,
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            Ok("feature\n"),
+        );
+        expect(
+            &mut command,
+            &["config", "--get", "lgit.protected"],
+            Err(()),
+        );
+        expect(&mut command, &["status", "--porcelain"], Ok(""));
+        expect(
+            &mut command,
+            &["rev-parse", "--verify", "refs/heads/feature"],
+            Ok("aaa111\n"),
+        );
+        expect(
+            &mut command,
+            &["rev-parse", "--verify", "refs/heads/main"],
+            Ok("bbb222\n"),
+        );
+        expect_refresh_base(&mut command);
+        expect(
+            &mut command,
+            &[
+                "-c",
+                "sequence.editor=:",
+                "rebase",
+                "--interactive",
+                "--autosquash",
+                "origin/main",
+            ],
+            Ok(""),
+        );
+        expect(&mut command, &["checkout", "main"], Ok(""));
+        expect(&mut command, &["merge", "--ff-only", "origin/main"], Ok(""));
+        expect(&mut command, &["merge", "--ff-only", "feature"], Ok(""));
+        expect(&mut command, &["config", "--get", "lgit.remote"], Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, inherit_stdio| args == ["push", "origin", "main"] && *inherit_stdio)
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        expect_restore(&mut command);
+
+        assert_eq!(
+            run(&command, "main", false, false),
+            Err(Some(
+                "Failed to push 'main' to 'origin', restored 'feature' and 'main'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_refuses_protected_branch() {
+        let mut command = MockCmd::new();
+        expect(
+            &mut command,
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            Ok("develop\n"),
+        );
+        expect(
+            &mut command,
+            &["config", "--get", "lgit.protected"],
+            Ok("main,develop\n"),
+        );
+
+        assert_eq!(
+            run(&command, "main", false, false),
+            Err(Some(
+                "Refusing to land protected branch 'develop'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_refuses_uncommitted_changes() {
+        let mut command = MockCmd::new();
+        expect(
+            &mut command,
+            &["symbolic-ref", "--short", "-q", "HEAD"],
+            Ok("feature\n"),
+        );
+        expect(
+            &mut command,
+            &["config", "--get", "lgit.protected"],
+            Err(()),
+        );
+        expect(
+            &mut command,
+            &["status", "--porcelain"],
+            Ok(" M file.txt\n"),
+        );
+
+        assert!(run(&command, "main", false, false).is_err());
+    }
+
+    #[test]
+    fn test_delete_branch_without_upstream() {
+        let mut command = MockCmd::new();
+        expect(
+            &mut command,
+            &["config", "--get", "branch.feature.remote"],
+            Err(()),
+        );
+        expect(
+            &mut command,
+            &["config", "--get", "branch.feature.merge"],
+            Err(()),
+        );
+        expect(
+            &mut command,
+            &["rev-parse", "--verify", "refs/heads/feature"],
+            Ok("aaa111\n"),
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "update-ref" && args[2] == "aaa111")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect(
+            &mut command,
+            &["config", "--get", "branch.feature.remote"],
+            Err(()),
+        );
+        expect(
+            &mut command,
+            &["config", "--get", "branch.feature.merge"],
+            Err(()),
+        );
+        expect(&mut command, &["branch", "-D", "feature"], Ok(""));
+
+        assert_eq!(delete_branch(&command, "feature", false), Ok(()));
+    }
+}
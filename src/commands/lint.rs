@@ -0,0 +1,263 @@
+use std::fs;
+
+use crate::commands::Exec;
+use crate::config;
+use crate::conventional::{self, Rules};
+use crate::parser::{self, Message};
+use crate::utils::{get_base, get_recorded_base};
+
+/// Object name git passes to the `pre-push` hook for a ref that doesn't exist
+const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+/// Checks the commit message in `file`, as git passes it to the `commit-msg` hook
+pub fn run_msg<T: Exec>(command: &T, file: &str, verbose: bool) -> Result<(), Option<String>> {
+    let message =
+        fs::read_to_string(file).map_err(|err| format!("Failed to read '{}': {}", file, err))?;
+
+    let problems = conventional::lint(&message, &Rules::load(command, verbose));
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(Some(format!(
+        "Invalid commit message:\n{}",
+        list(&problems)
+    )))
+}
+
+/// Checks the messages of every commit of the current branch since `base`, including leftover
+/// `fixup!` commits unless `lgit.lintFixups` is turned off
+pub fn run_branch<T: Exec>(command: &T, base: &str, verbose: bool) -> Result<(), Option<String>> {
+    lint_range(command, &format!("{base}..HEAD"), verbose)
+}
+
+/// Checks the commits a push sends, given the `<local ref> <local sha> <remote ref> <remote sha>`
+/// lines git passes to the `pre-push` hook. For a new remote branch, or one with commits not
+/// fetched yet, those are the commits since `base` or the base of the pushed branch.
+pub fn run_pre_push<T: Exec>(
+    command: &T,
+    refs: &str,
+    base: Option<String>,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let mut reports = Vec::new();
+
+    for line in refs.lines() {
+        let [local_ref, local_sha, _, remote_sha] = line.split_whitespace().collect::<Vec<_>>()[..]
+        else {
+            continue;
+        };
+
+        // Deleting a remote branch pushes no commits
+        if local_sha == NULL_SHA {
+            continue;
+        }
+
+        let known = remote_sha != NULL_SHA
+            && command
+                .exec(
+                    &["cat-file", "-e", &format!("{remote_sha}^{{commit}}")],
+                    verbose,
+                    false,
+                )
+                .is_ok();
+
+        let since = match known {
+            true => remote_sha.to_string(),
+            false => {
+                let branch = local_ref.strip_prefix("refs/heads/").unwrap_or(local_ref);
+                let base = base
+                    .clone()
+                    .or_else(|| get_recorded_base(command, branch, verbose));
+
+                get_base(command, base, verbose)
+            }
+        };
+
+        if let Err(Some(report)) = lint_range(command, &format!("{since}..{local_sha}"), verbose) {
+            reports.push(format!("{local_ref}:\n{report}"));
+        }
+    }
+
+    match reports.is_empty() {
+        true => Ok(()),
+        false => Err(Some(reports.join("\n\n"))),
+    }
+}
+
+fn lint_range<T: Exec>(command: &T, range: &str, verbose: bool) -> Result<(), Option<String>> {
+    let messages = parser::messages(command, &["--reverse", range], verbose)
+        .map_err(|()| format!("Failed to list commits in '{}'", range))?;
+
+    let rules = Rules::load(command, verbose);
+    let fixups = config::get_bool(command, "lgit.lintFixups", verbose).unwrap_or(true);
+
+    let reports: Vec<String> = messages
+        .iter()
+        .filter_map(|message| {
+            let problems = lint_commit(message, &rules, fixups);
+
+            (!problems.is_empty()).then(|| {
+                format!(
+                    "{} {}\n{}",
+                    &message.sha[..message.sha.len().min(7)],
+                    message.message.lines().next().unwrap_or_default(),
+                    list(&problems)
+                )
+            })
+        })
+        .collect();
+
+    if reports.is_empty() {
+        println!("All {} commit messages pass", messages.len());
+        return Ok(());
+    }
+
+    Err(Some(format!(
+        "{}\n\n{} of {} commit messages have problems",
+        reports.join("\n"),
+        reports.len(),
+        messages.len()
+    )))
+}
+
+fn lint_commit(message: &Message, rules: &Rules, fixups: bool) -> Vec<String> {
+    let subject = message.message.lines().next().unwrap_or_default();
+
+    if fixups && conventional::is_fixup(subject) {
+        return vec!["Squash this commit into its target with 'lgit autosquash'".to_string()];
+    }
+
+    conventional::lint(&message.message, rules)
+}
+
+fn list(problems: &[String]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("  - {problem}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn expect_rules(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "config")
+            .returning(|_, _, _| Err(()));
+    }
+
+    #[test]
+    fn test_run_branch() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["log", "-z", "--format=%H%x00%B", "--reverse", "main..HEAD"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("abc1234567\0feat: add login\n\0\n\
+                    def4567890\0fixup! feat: add login\n\0\n\
+                    fed7890123\0Added logout.\n\0"
+                    .to_string())
+            });
+        expect_rules(&mut command);
+
+        assert_eq!(
+            run_branch(&command, "main", false),
+            Err(Some(
+                "def4567 fixup! feat: add login\n\
+                 \x20 - Squash this commit into its target with 'lgit autosquash'\n\
+                 fed7890 Added logout.\n\
+                 \x20 - The subject 'Added logout.' doesn't follow Conventional Commits, e.g. 'feat(scope): add something'\n\
+                 \n\
+                 2 of 3 commit messages have problems"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_pre_push() {
+        let new = "1111111111111111111111111111111111111111";
+        let refs = format!(
+            "refs/heads/feature {new} refs/heads/feature abc1234567\n\
+             refs/heads/other {new} refs/heads/other {NULL_SHA}\n\
+             (delete) {NULL_SHA} refs/heads/gone def4567890\n"
+        );
+
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["cat-file", "-e", "abc1234567^{commit}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| args[0] == "log" && args[4] == format!("abc1234567..{new}"))
+            .times(1)
+            .returning(|_, _, _| Ok("fed7890123\0fixup! feat: add login\n\0".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.other.lgitBase"])
+            .times(1)
+            .returning(|_, _, _| Ok("develop\n".to_string()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| args[0] == "log" && args[4] == format!("develop..{new}"))
+            .times(1)
+            .returning(|_, _, _| Ok("abc1234567\0feat: add logout\n\0".to_string()));
+        expect_rules(&mut command);
+
+        assert_eq!(
+            run_pre_push(&command, &refs, None, false),
+            Err(Some(
+                "refs/heads/feature:\n\
+                 fed7890 fixup! feat: add login\n\
+                 \x20 - Squash this commit into its target with 'lgit autosquash'\n\
+                 \n\
+                 1 of 1 commit messages have problems"
+                    .to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_lint_commit_allows_fixups() {
+        let message = Message {
+            sha: "abc1234567".to_string(),
+            message: "fixup! feat: add login\n".to_string(),
+        };
+
+        assert_eq!(
+            lint_commit(&message, &Rules::default(), false),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_run_msg() {
+        let file = std::env::temp_dir().join(format!("lgit-lint-msg-{}", std::process::id()));
+        fs::write(
+            &file,
+            "fix: add a check\n\n# Please enter the commit message\n",
+        )
+        .unwrap();
+
+        let mut command = MockCmd::new();
+        expect_rules(&mut command);
+
+        let result = run_msg(&command, &file.to_string_lossy(), false);
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(result, Ok(()));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::commands::Exec;
+use crate::config;
+use crate::utils::get_current_branch;
+
+pub fn run<T: Exec>(command: &T, base: &str, verbose: bool) -> Result<(), Option<String>> {
+    let branch = get_current_branch(command, verbose)
+        .ok_or_else(|| "Failed to publish, HEAD is not on a branch".to_string())?;
+
+    publish(command, &branch, base, verbose)?;
+
+    Ok(())
+}
+
+/// Pushes `branch` and sets it up to track the pushed branch
+pub fn publish<T: Exec>(
+    command: &T,
+    branch: &str,
+    base: &str,
+    verbose: bool,
+) -> Result<(), String> {
+    let remote = get_publish_remote(command, base, verbose);
+
+    // Not captured, so that hints such as links to open a pull request are shown
+    command
+        .exec(&["push", "--set-upstream", &remote, branch], verbose, true)
+        .map_err(|()| format!("Failed to publish branch '{}' to '{}'", branch, remote))?;
+
+    println!("Published {branch} to {remote}");
+
+    Ok(())
+}
+
+/// Remote to publish to: `lgit.remote` if configured, otherwise the remote the base branch
+/// tracks, falling back to `origin`
+fn get_publish_remote<T: Exec>(command: &T, base: &str, verbose: bool) -> String {
+    config::get(command, "lgit.remote", verbose)
+        .or_else(|| config::get(command, &format!("branch.{base}.remote"), verbose))
+        // A base tracking another local branch has `.` as its remote
+        .filter(|remote| remote != ".")
+        .unwrap_or_else(|| "origin".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn cmd_remote_config(
+        lgit_remote: Option<&'static str>,
+        base_remote: Option<&'static str>,
+    ) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["config", "--get", "lgit.remote"] && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| lgit_remote.map(str::to_string).ok_or(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.main.remote"])
+            .times(0..=1)
+            .returning(move |_, _, _| base_remote.map(str::to_string).ok_or(()));
+
+        command
+    }
+
+    #[test]
+    fn test_get_publish_remote_from_config() {
+        let command = cmd_remote_config(Some("fork\n"), None);
+
+        assert_eq!(get_publish_remote(&command, "main", false), "fork");
+    }
+
+    #[test]
+    fn test_get_publish_remote_from_base_upstream() {
+        let command = cmd_remote_config(None, Some("upstream\n"));
+
+        assert_eq!(get_publish_remote(&command, "main", false), "upstream");
+    }
+
+    #[test]
+    fn test_get_publish_remote_defaults_to_origin() {
+        let command = cmd_remote_config(None, Some(".\n"));
+
+        assert_eq!(get_publish_remote(&command, "main", false), "origin");
+    }
+
+    #[test]
+    fn test_run_publishes_current_branch() {
+        let mut command = cmd_remote_config(None, Some("upstream\n"));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stdio| {
+                args == ["push", "--set-upstream", "upstream", "feature"]
+                    && !(*verbose)
+                    && *inherit_stdio
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(run(&command, "main", false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_detached_head() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert!(run(&command, "main", false).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Confirm;
+
+use crate::commands::Exec;
+use crate::config;
+use crate::parser::{self, Commit};
+use crate::utils::{get_current_branch, matches_protected, protected_patterns};
+
+/// Where a branch is pushed to, and the tip of the remote branch as of the last fetch or push
+#[derive(Debug, PartialEq)]
+struct Upstream {
+    remote: String,
+    /// Full name of the remote branch, e.g. `refs/heads/feature`
+    merge: String,
+    sha: String,
+}
+
+/// Pushes the current branch to its upstream, force-pushing with a lease when its history was
+/// rewritten since the last push
+pub fn run<T: Exec>(command: &T, verbose: bool) -> Result<(), Option<String>> {
+    let branch = get_current_branch(command, verbose)
+        .ok_or_else(|| "Failed to push, HEAD is not on a branch".to_string())?;
+
+    let upstream = get_upstream(command, &branch, verbose)?;
+    let refspec = format!("{branch}:{}", upstream.merge);
+
+    if !is_rewritten(command, &upstream, verbose) {
+        command
+            .exec(&["push", &upstream.remote, &refspec], verbose, true)
+            .map_err(|()| format!("Failed to push '{}'", branch))?;
+
+        return Ok(());
+    }
+
+    // A local branch may push to a protected one of another name, e.g. `wip` tracking `origin/main`
+    let protected = protected_patterns(command, verbose);
+
+    if let Some(name) = [branch.as_str(), upstream.name()]
+        .into_iter()
+        .find(|name| matches_protected(name, &protected))
+    {
+        return Err(Some(format!(
+            "Refusing to force-push protected branch '{}'",
+            name
+        )));
+    }
+
+    let dropped = get_dropped(command, &upstream, verbose)?;
+
+    if !dropped.is_empty() {
+        println!(
+            "Force-pushing drops these commits from {}:",
+            display_name(&upstream)
+        );
+
+        for commit in &dropped {
+            println!("  {commit}");
+        }
+
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Force-push anyway?")
+            .default(false)
+            .interact()
+            .map_err(|err| format!("Failed to confirm force-push: {}", err))?;
+
+        if !confirmed {
+            return Err(Some("Aborted, nothing was pushed".to_string()));
+        }
+    }
+
+    // The lease fails if someone pushed since our last fetch, and `--force-if-includes` if we
+    // fetched their commits but never integrated them. The latter is ignored when the lease names
+    // the expected commit, so git has to take it from the remote-tracking branch itself.
+    let lease = format!("--force-with-lease={}", upstream.merge);
+
+    command
+        .exec(
+            &[
+                "push",
+                &lease,
+                "--force-if-includes",
+                &upstream.remote,
+                &refspec,
+            ],
+            verbose,
+            true,
+        )
+        .map_err(|()| {
+            format!(
+                "Failed to force-push '{}' (if {} changed since it was last fetched, fetch and review it first)",
+                branch,
+                display_name(&upstream)
+            )
+        })?;
+
+    Ok(())
+}
+
+fn get_upstream<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<Upstream, String> {
+    let no_upstream = || format!("Branch '{}' has no upstream, use 'lgit publish'", branch);
+
+    let remote = config::get(command, &format!("branch.{branch}.remote"), verbose)
+        .ok_or_else(no_upstream)?;
+    let merge =
+        config::get(command, &format!("branch.{branch}.merge"), verbose).ok_or_else(no_upstream)?;
+
+    let sha = command
+        .exec(
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{branch}@{{upstream}}"),
+            ],
+            verbose,
+            false,
+        )
+        .map_err(|()| {
+            format!(
+                "The upstream of branch '{}' is gone, use 'lgit publish' to push it again",
+                branch
+            )
+        })?;
+
+    Ok(Upstream {
+        remote,
+        merge,
+        sha: sha.trim().to_string(),
+    })
+}
+
+/// Whether the remote branch is no longer part of the local history, i.e. pushing needs force
+fn is_rewritten<T: Exec>(command: &T, upstream: &Upstream, verbose: bool) -> bool {
+    command
+        .exec(
+            &["merge-base", "--is-ancestor", &upstream.sha, "HEAD"],
+            verbose,
+            false,
+        )
+        .is_err()
+}
+
+/// Lists the commits of the remote branch that have no equivalent change in the local branch,
+/// leaving out the ones that were merely rebased
+fn get_dropped<T: Exec>(
+    command: &T,
+    upstream: &Upstream,
+    verbose: bool,
+) -> Result<Vec<Commit>, String> {
+    parser::commits(
+        command,
+        &[
+            "--cherry-pick",
+            "--right-only",
+            "--no-merges",
+            &format!("HEAD...{}", upstream.sha),
+        ],
+        verbose,
+    )
+    .map_err(|()| format!("Failed to compare with {}", display_name(upstream)))
+}
+
+impl Upstream {
+    /// Name of the remote branch, e.g. `feature`
+    fn name(&self) -> &str {
+        self.merge
+            .strip_prefix("refs/heads/")
+            .unwrap_or(&self.merge)
+    }
+}
+
+fn display_name(upstream: &Upstream) -> String {
+    format!("{}/{}", upstream.remote, upstream.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn cmd_upstream(rewritten: bool) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["symbolic-ref", "--short", "-q", "HEAD"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.remote"])
+            .times(1)
+            .returning(|_, _, _| Ok("origin\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.merge"])
+            .times(1)
+            .returning(|_, _, _| Ok("refs/heads/feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "--quiet", "feature@{upstream}"])
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["merge-base", "--is-ancestor", "abc123", "HEAD"])
+            .times(1)
+            .returning(move |_, _, _| {
+                if rewritten {
+                    Err(())
+                } else {
+                    Ok(String::new())
+                }
+            });
+
+        command
+    }
+
+    fn expect_protected(command: &mut MockCmd, patterns: &'static str) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.protected"])
+            .times(1)
+            .returning(move |_, _, _| Ok(patterns.to_string()));
+    }
+
+    #[test]
+    fn test_run_fast_forward_push() {
+        let mut command = cmd_upstream(false);
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stdio| {
+                args == ["push", "origin", "feature:refs/heads/feature"]
+                    && !(*verbose)
+                    && *inherit_stdio
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(run(&command, false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_force_push_with_lease() {
+        let mut command = cmd_upstream(true);
+        expect_protected(&mut command, "main");
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "log" && args[args.len() - 1] == "HEAD...abc123")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, inherit_stdio| {
+                args == [
+                    "push",
+                    "--force-with-lease=refs/heads/feature",
+                    "--force-if-includes",
+                    "origin",
+                    "feature:refs/heads/feature",
+                ] && *inherit_stdio
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(run(&command, false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_refuses_protected_branch() {
+        let mut command = cmd_upstream(true);
+        expect_protected(&mut command, "main,feat*");
+
+        assert_eq!(
+            run(&command, false),
+            Err(Some(
+                "Refusing to force-push protected branch 'feature'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_refuses_protected_upstream() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Ok("wip\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.wip.remote"])
+            .times(1)
+            .returning(|_, _, _| Ok("origin\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.wip.merge"])
+            .times(1)
+            .returning(|_, _, _| Ok("refs/heads/main\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "rev-parse")
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "merge-base")
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        expect_protected(&mut command, "main");
+
+        assert_eq!(
+            run(&command, false),
+            Err(Some(
+                "Refusing to force-push protected branch 'main'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_without_upstream() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.remote"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert_eq!(
+            run(&command, false),
+            Err(Some(
+                "Branch 'feature' has no upstream, use 'lgit publish'".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_dropped() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "log",
+                    "-z",
+                    "--format=%h%x00%s",
+                    "--cherry-pick",
+                    "--right-only",
+                    "--no-merges",
+                    "HEAD...abc123",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("def456\0Teammate's fix\0".to_string()));
+
+        let upstream = Upstream {
+            remote: "origin".to_string(),
+            merge: "refs/heads/feature".to_string(),
+            sha: "abc123".to_string(),
+        };
+
+        let dropped = get_dropped(&command, &upstream, false);
+
+        assert_eq!(
+            dropped,
+            Ok(vec![Commit {
+                sha: "def456".to_string(),
+                subject: "Teammate's fix".to_string()
+            }])
+        );
+    }
+}
@@ -1,25 +1,376 @@
+use std::fmt;
+
+use clap::ValueEnum;
+
+use crate::cli::Strategy;
 use crate::commands::Exec;
-use crate::utils::{refresh_base, stash, unstash};
+use crate::config;
+use crate::preflight::{self, Operation};
+use crate::utils::{
+    ahead_behind, get_current_branch, get_recorded_bases, is_protected, refresh_base, stash,
+    unstash,
+};
+
+/// SHA of the stash holding changes of a rebase or merge stopped on conflicts, so that resuming it
+/// restores them
+pub const RESUME_STASH: &str = "lgit.resumeStash";
+
+/// Where `lgit rebase --all` left off when it stopped on conflicts, see `Queue`
+const RESUME_ONTO: &str = "lgit.resumeOnto";
+const RESUME_BRANCH: &str = "lgit.resumeBranch";
+const RESUME_QUEUE: &str = "lgit.resumeQueue";
+
+/// Branches `lgit rebase --all` still has to rebase onto `onto`, and the branch to return to
+/// afterwards
+#[derive(Debug, PartialEq)]
+struct Queue {
+    onto: String,
+    original: String,
+    pending: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Report {
+    rebased: Vec<String>,
+    /// Branches with the reason they were left alone
+    skipped: Vec<String>,
+    conflicted: Option<String>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines = [
+            ("Rebased", self.rebased.join(", ")),
+            ("Skipped", self.skipped.join(", ")),
+            ("Conflicted", self.conflicted.clone().unwrap_or_default()),
+        ];
+
+        let lines: Vec<String> = lines
+            .iter()
+            .filter(|(_, branches)| !branches.is_empty())
+            .map(|(label, branches)| format!("{label}: {branches}"))
+            .collect();
+
+        if lines.is_empty() {
+            write!(f, "No branches to rebase")
+        } else {
+            write!(f, "{}", lines.join("\n"))
+        }
+    }
+}
+
+/// Brings the current branch up to date with the freshly fetched `base`, by rebasing onto it or
+/// merging it in. Uncommitted changes are stashed meanwhile and restored once done, which is
+/// postponed to `lgit rebase --continue` if the operation stops on conflicts. With `exec`, the
+/// command runs after every rebased commit and the rebase stops where it fails.
+pub fn run<T: Exec>(
+    command: &T,
+    base: &str,
+    strategy: &Strategy,
+    exec: Option<&str>,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    if exec.is_some() && *strategy == Strategy::Merge {
+        return Err(Some(
+            "Running a command on each commit needs the rebase strategy".to_string(),
+        ));
+    }
+
+    let operation = match strategy {
+        Strategy::Rebase => "rebase",
+        Strategy::Merge => "update",
+    };
+
+    let stashed = stash(command, operation, verbose)?;
+
+    set_resume_stash(command, stashed.as_deref(), verbose);
+
+    let Ok(upstream) = refresh_base(command, base, verbose) else {
+        finish(command, stashed.as_deref(), verbose)?;
 
-pub fn run<T: Exec>(command: &T, base: &str, verbose: bool) -> Result<(), Option<String>> {
-    let unsaved_changes = stash(command, verbose)?;
+        return Err(Some(format!("Failed to refresh base branch '{}'", base)));
+    };
 
-    refresh_base(command, base, verbose)
-        .map_err(|()| format!("Failed to refresh base branch '{}'", base))?;
+    let mut args = match strategy {
+        Strategy::Rebase => vec!["rebase"],
+        Strategy::Merge => vec!["merge", "--no-edit"],
+    };
+
+    if let Some(exec) = exec {
+        args.extend(["--exec", exec]);
+    }
+
+    args.push(&upstream);
+
+    // The output of the command shows why a commit failed it
+    if command.exec(&args, verbose, exec.is_some()).is_err() {
+        // Stopped on conflicts, the stash is restored when resuming
+        if let Some(operation) = in_progress(command, verbose) {
+            return Err(Some(match exec {
+                Some(exec) => format!(
+                    "The rebase stopped on conflicts or a commit failing '{}', fix it and run 'lgit rebase --continue' (or 'lgit rebase --abort')",
+                    exec
+                ),
+                None => conflict_hint(operation),
+            }));
+        }
+
+        finish(command, stashed.as_deref(), verbose)?;
+
+        return Err(Some(match strategy {
+            Strategy::Rebase => format!("Failed to rebase onto '{}'", upstream),
+            Strategy::Merge => format!("Failed to merge '{}'", upstream),
+        }));
+    }
 
+    finish(command, stashed.as_deref(), verbose)?;
+
+    match strategy {
+        Strategy::Rebase => println!("Rebased onto {upstream}"),
+        Strategy::Merge => println!("Merged {upstream}"),
+    }
+
+    Ok(())
+}
+
+/// Continues or aborts the rebase or merge that stopped on conflicts, then restores the changes
+/// stashed before it started
+pub fn resume<T: Exec>(command: &T, abort: bool, verbose: bool) -> Result<(), Option<String>> {
+    let operation = in_progress(command, verbose)
+        .ok_or_else(|| "No rebase or merge in progress".to_string())?;
+
+    let action = if abort { "--abort" } else { "--continue" };
+
+    // Keeps the prepared commit messages instead of opening an editor
     command
-        .exec(&["checkout", "-"], verbose, false)
-        .map_err(|()| "Failed to checkout back to initial branch (git checkout -)".to_string())?;
+        .exec(
+            &["-c", "core.editor=true", operation, action],
+            verbose,
+            false,
+        )
+        .map_err(|()| conflict_hint(operation))?;
+
+    if let Some(queue) = load_queue(command, verbose) {
+        if abort {
+            clear_queue(command, verbose);
+
+            command
+                .exec(&["checkout", &queue.original], verbose, false)
+                .map_err(|()| format!("Failed to checkout '{}'", queue.original))?;
+        } else {
+            let mut report = Report::default();
+
+            // The branch that stopped on conflicts is rebased now
+            report.rebased.extend(get_current_branch(command, verbose));
+
+            let result = rebase_queue(command, &queue, &mut report, verbose);
+
+            println!("{report}");
+
+            result?;
+        }
+    }
+
+    let stashed = config::get(command, RESUME_STASH, verbose);
+
+    finish(command, stashed.as_deref(), verbose)?;
+
+    Ok(())
+}
+
+/// Rebases every local branch created off `base` with `lgit branch` onto the freshly fetched base,
+/// skipping merged and protected ones, and reports the outcome. Stops at the first conflict, after
+/// which `lgit rebase --continue` carries on with the remaining branches.
+pub fn run_all<T: Exec>(command: &T, base: &str, verbose: bool) -> Result<(), Option<String>> {
+    let original = get_current_branch(command, verbose)
+        .ok_or_else(|| "Failed to rebase branches, HEAD is not on a branch".to_string())?;
+
+    let stashed = stash(command, "rebase --all", verbose)?;
+
+    set_resume_stash(command, stashed.as_deref(), verbose);
+
+    let Ok(onto) = refresh_base(command, base, verbose) else {
+        finish(command, stashed.as_deref(), verbose)?;
+
+        return Err(Some(format!("Failed to refresh base branch '{}'", base)));
+    };
+
+    let mut pending: Vec<String> = get_recorded_bases(command, verbose)
+        .into_iter()
+        .filter(|(_, recorded)| recorded == base)
+        .map(|(branch, _)| branch)
+        .collect();
+
+    pending.sort();
+
+    let queue = Queue {
+        onto,
+        original,
+        pending,
+    };
+
+    let mut report = Report::default();
+    let result = rebase_queue(command, &queue, &mut report, verbose);
+
+    println!("{report}");
+
+    // Stopped on conflicts, the stash is restored when resuming
+    result?;
+
+    finish(command, stashed.as_deref(), verbose)?;
+
+    Ok(())
+}
+
+/// Rebases the queued branches one after the other and returns to the original branch. On
+/// conflicts, the branches left are saved for `lgit rebase --continue`.
+fn rebase_queue<T: Exec>(
+    command: &T,
+    queue: &Queue,
+    report: &mut Report,
+    verbose: bool,
+) -> Result<(), String> {
+    for (index, branch) in queue.pending.iter().enumerate() {
+        if let Some(reason) = skip_reason(command, branch, &queue.onto, verbose) {
+            report.skipped.push(format!("{branch} ({reason})"));
+            continue;
+        }
+
+        if command
+            .exec(&["rebase", &queue.onto, branch], verbose, false)
+            .is_ok()
+        {
+            report.rebased.push(branch.clone());
+            continue;
+        }
+
+        if in_progress(command, verbose).is_some() {
+            report.conflicted = Some(branch.clone());
+
+            save_queue(
+                command,
+                &Queue {
+                    onto: queue.onto.clone(),
+                    original: queue.original.clone(),
+                    pending: queue.pending[index + 1..].to_vec(),
+                },
+                verbose,
+            );
+
+            return Err(conflict_hint("rebase"));
+        }
+
+        // E.g. checked out in another worktree
+        report.skipped.push(format!("{branch} (failed to rebase)"));
+    }
+
+    clear_queue(command, verbose);
 
     command
-        .exec(&["rebase", base], verbose, false)
-        .map_err(|()| format!("Failed to rebase onto '{}'", base))?;
+        .exec(&["checkout", &queue.original], verbose, false)
+        .map_err(|()| format!("Failed to checkout '{}'", queue.original))?;
+
+    Ok(())
+}
+
+fn skip_reason<T: Exec>(
+    command: &T,
+    branch: &str,
+    onto: &str,
+    verbose: bool,
+) -> Option<&'static str> {
+    if is_protected(command, branch, verbose) {
+        return Some("protected");
+    }
+
+    let strategy = config::get(command, &format!("branch.{branch}.lgitStrategy"), verbose);
+
+    if strategy.is_some_and(|strategy| strategy.eq_ignore_ascii_case("merge")) {
+        return Some("merge strategy");
+    }
+
+    match ahead_behind(command, &format!("refs/heads/{branch}"), onto, verbose) {
+        Ok((0, _)) => Some("merged"),
+        Ok((_, 0)) => Some("up to date"),
+        Ok(_) => None,
+        Err(()) => Some("failed to compare"),
+    }
+}
+
+fn save_queue<T: Exec>(command: &T, queue: &Queue, verbose: bool) {
+    let pending = queue.pending.join(" ");
+
+    // Only bookkeeping, resuming without it merely leaves the remaining branches as they are
+    for (key, value) in [
+        (RESUME_ONTO, queue.onto.as_str()),
+        (RESUME_BRANCH, queue.original.as_str()),
+        (RESUME_QUEUE, pending.as_str()),
+    ] {
+        let _ = command.exec(&["config", key, value], verbose, false);
+    }
+}
+
+fn load_queue<T: Exec>(command: &T, verbose: bool) -> Option<Queue> {
+    Some(Queue {
+        onto: config::get(command, RESUME_ONTO, verbose)?,
+        original: config::get(command, RESUME_BRANCH, verbose)?,
+        pending: config::get(command, RESUME_QUEUE, verbose)
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+    })
+}
+
+fn clear_queue<T: Exec>(command: &T, verbose: bool) {
+    for key in [RESUME_ONTO, RESUME_BRANCH, RESUME_QUEUE] {
+        let _ = command.exec(&["config", "--unset", key], verbose, false);
+    }
+}
+
+/// Strategy for the current branch: `branch.<name>.lgitStrategy`, then `lgit.strategy`, falling
+/// back to rebasing
+pub fn get_strategy<T: Exec>(command: &T, verbose: bool) -> Strategy {
+    get_current_branch(command, verbose)
+        .and_then(|branch| config::get(command, &format!("branch.{branch}.lgitStrategy"), verbose))
+        .or_else(|| config::get(command, "lgit.strategy", verbose))
+        .and_then(|strategy| Strategy::from_str(&strategy, true).ok())
+        .unwrap_or(Strategy::Rebase)
+}
+
+fn conflict_hint(operation: &str) -> String {
+    format!(
+        "The {} stopped on conflicts, resolve them and run 'lgit rebase --continue' (or 'lgit rebase --abort')",
+        operation
+    )
+}
 
-    if unsaved_changes {
-        unstash(command, verbose)?;
+/// The git operation waiting for conflicts to be resolved, if any
+fn in_progress<T: Exec>(command: &T, verbose: bool) -> Option<&'static str> {
+    match preflight::in_progress(command, verbose) {
+        Some(Operation::Rebase) => Some("rebase"),
+        Some(Operation::Merge) => Some("merge"),
+        _ => None,
     }
+}
 
-    println!("Rebased onto {base}");
+fn set_resume_stash<T: Exec>(command: &T, stashed: Option<&str>, verbose: bool) {
+    // Only bookkeeping, resuming without it merely leaves the stash for the user to pop
+    let _ = match stashed {
+        Some(sha) => command.exec(&["config", RESUME_STASH, sha], verbose, false),
+        None => command.exec(&["config", "--unset", RESUME_STASH], verbose, false),
+    };
+}
+
+fn finish<T: Exec>(
+    command: &T,
+    stashed: Option<&str>,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    if let Some(sha) = stashed {
+        set_resume_stash(command, None, verbose);
+        unstash(command, sha, verbose)?;
+    }
 
     Ok(())
 }
@@ -29,128 +380,195 @@ mod tests {
     use super::*;
     use crate::commands::MockCmd;
 
-    #[test]
-    fn test_run_success_without_stash() {
-        let mut command = MockCmd::new();
-
-        // Mock stash check - no changes
+    fn expect_refresh_base(command: &mut MockCmd, fetched: bool) {
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.fastForwardBase"])
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
-
-        // Mock refresh_base - checkout base
+            .returning(|_, _, _| Err(()));
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(|_, _, _| Err(()));
 
-        // Mock refresh_base - pull
+        // The local base can't be fast-forwarded, e.g. because it has diverged
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
+                args == ["fetch", "origin", "main:main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
-
-        // Mock checkout back to original branch
+            .returning(|_, _, _| Err(()));
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "-"] && !(*verbose) && !(*inherit_stderr)
+                args == ["fetch", "origin", "main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(move |_, _, _| if fetched { Ok(String::new()) } else { Err(()) });
+
+        if fetched {
+            command
+                .expect_exec()
+                .withf(|args, _, _| {
+                    args == [
+                        "show-ref",
+                        "--verify",
+                        "--quiet",
+                        "refs/remotes/origin/main",
+                    ]
+                })
+                .times(1)
+                .returning(|_, _, _| Ok(String::new()));
+        }
+    }
 
-        // Mock rebase
+    fn expect_config(command: &mut MockCmd, args: &'static [&'static str]) {
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["rebase", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(move |actual, _, _| actual == args)
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
-
-        let result = run(&command, "main", false);
-
-        assert!(result.is_ok());
     }
 
-    #[test]
-    fn test_run_success_with_stash() {
-        let mut command = MockCmd::new();
-
-        // Mock stash check - has changes
+    fn expect_stash_push(command: &mut MockCmd) {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
+                args.starts_with(&["stash", "push", "--include-untracked"])
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("M file.txt".to_string()));
+            .returning(|_, _, _| Ok("Saved working directory".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "--quiet", "refs/stash"])
+            .times(2)
+            .returning({
+                // No stash before pushing one, the new stash after
+                let mut calls = 0;
+                move |_, _, _| {
+                    calls += 1;
+                    (calls > 1).then(|| "abc123\n".to_string()).ok_or(())
+                }
+            });
+    }
 
-        // Mock stash save
+    fn expect_stash_pop(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["stash", "list"]))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(
+                    "stash@{0}\0abc123\x001760882700\0On feature: lgit-stash:rebase 2026-10-19 14:05 UTC\0"
+                        .to_string(),
+                )
+            });
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "-u"] && !(*verbose) && !(*inherit_stderr)
+                args == ["stash", "pop", "stash@{0}"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("Saved working directory".to_string()));
+            .returning(|_, _, _| Ok("Applied stash".to_string()));
+    }
 
-        // Mock refresh_base - checkout base
+    /// Mocks the git directory with only `state_file` present, see `preflight`
+    fn expect_in_progress(command: &mut MockCmd, state_file: Option<&'static str>) {
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args.starts_with(&["rev-parse", "--git-path"]))
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(move |_, _, _| {
+                let paths: Vec<String> = [
+                    "rebase-merge",
+                    "rebase-apply",
+                    "MERGE_HEAD",
+                    "CHERRY_PICK_HEAD",
+                    "BISECT_LOG",
+                    "index.lock",
+                ]
+                .iter()
+                .map(|file| match state_file {
+                    // An existing directory stands in for the state file
+                    Some(state_file) if state_file == *file => {
+                        env!("CARGO_MANIFEST_DIR").to_string()
+                    }
+                    _ => format!("/nonexistent/.git/{file}"),
+                })
+                .collect();
+
+                Ok(paths.join("\n"))
+            });
+    }
 
-        // Mock refresh_base - pull
+    #[test]
+    fn test_run_success_without_stash() {
+        let mut command = MockCmd::new();
+
+        // Mock stash check - no changes
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
+                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        // Mock checkout back to original branch
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
+
+        expect_refresh_base(&mut command, true);
+
+        // Mock rebase onto the remote-tracking base
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "-"] && !(*verbose) && !(*inherit_stderr)
+                args == ["rebase", "origin/main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        // Mock rebase
+        let result = run(&command, "main", &Strategy::Rebase, None, false);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_success_with_stash() {
+        let mut command = MockCmd::new();
+
+        // Mock stash check - has changes
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["rebase", "main"] && !(*verbose) && !(*inherit_stderr)
+                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(|_, _, _| Ok("M file.txt".to_string()));
+
+        expect_stash_push(&mut command);
+
+        expect_config(&mut command, &["config", "lgit.resumeStash", "abc123"]);
 
-        // Mock unstash
+        expect_refresh_base(&mut command, true);
+
+        // Mock rebase
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "pop"] && !(*verbose) && !(*inherit_stderr)
+                args == ["rebase", "origin/main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok("Applied stash".to_string()));
+            .returning(|_, _, _| Ok(String::new()));
 
-        let result = run(&command, "main", false);
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
+
+        expect_stash_pop(&mut command);
+
+        let result = run(&command, "main", &Strategy::Rebase, None, false);
 
         assert!(result.is_ok());
     }
@@ -168,7 +586,7 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Err(()));
 
-        let result = run(&command, "main", false);
+        let result = run(&command, "main", &Strategy::Rebase, None, false);
 
         assert!(result.is_err());
     }
@@ -186,22 +604,20 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        // Mock refresh_base - checkout failure
-        command
-            .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
-            .times(1)
-            .returning(|_, _, _| Err(()));
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
 
-        let result = run(&command, "main", false);
+        expect_refresh_base(&mut command, false);
 
-        assert!(result.is_err());
+        let result = run(&command, "main", &Strategy::Rebase, None, false);
+
+        assert_eq!(
+            result,
+            Err(Some("Failed to refresh base branch 'main'".to_string()))
+        );
     }
 
     #[test]
-    fn test_run_checkout_back_failure() {
+    fn test_run_rebase_failure() {
         let mut command = MockCmd::new();
 
         // Mock stash check - no changes
@@ -213,88 +629,287 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        // Mock refresh_base - checkout base
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
+
+        expect_refresh_base(&mut command, true);
+
+        // Mock rebase - failure
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
+                args == ["rebase", "origin/main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(|_, _, _| Err(()));
+
+        expect_in_progress(&mut command, None);
 
-        // Mock refresh_base - pull
+        let result = run(&command, "main", &Strategy::Rebase, None, false);
+
+        assert_eq!(
+            result,
+            Err(Some("Failed to rebase onto 'origin/main'".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_run_merge_conflict_keeps_stash() {
+        let mut command = MockCmd::new();
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["status", "--porcelain"])
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
-
-        // Mock checkout back - failure
+            .returning(|_, _, _| Ok("M file.txt".to_string()));
+        expect_stash_push(&mut command);
+        expect_config(&mut command, &["config", "lgit.resumeStash", "abc123"]);
+        expect_refresh_base(&mut command, true);
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "-"] && !(*verbose) && !(*inherit_stderr)
+                args == ["merge", "--no-edit", "origin/main"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Err(()));
+        expect_in_progress(&mut command, Some("MERGE_HEAD"));
 
-        let result = run(&command, "main", false);
+        let result = run(&command, "main", &Strategy::Merge, None, false);
 
-        assert!(result.is_err());
+        assert_eq!(
+            result,
+            Err(Some(
+                "The merge stopped on conflicts, resolve them and run 'lgit rebase --continue' (or 'lgit rebase --abort')".to_string()
+            ))
+        );
     }
 
     #[test]
-    fn test_run_rebase_failure() {
+    fn test_resume_continue_restores_stash() {
         let mut command = MockCmd::new();
 
-        // Mock stash check - no changes
+        expect_in_progress(&mut command, Some("rebase-merge"));
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["status", "--porcelain"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["-c", "core.editor=true", "rebase", "--continue"])
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.resumeOnto"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.resumeStash"])
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
+        expect_stash_pop(&mut command);
 
-        // Mock refresh_base - checkout base
+        assert_eq!(resume(&command, false, false), Ok(()));
+    }
+
+    #[test]
+    fn test_resume_nothing_in_progress() {
+        let mut command = MockCmd::new();
+        expect_in_progress(&mut command, None);
+
+        assert_eq!(
+            resume(&command, true, false),
+            Err(Some("No rebase or merge in progress".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_get_strategy() {
+        let mut command = MockCmd::new();
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.lgitStrategy"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.strategy"])
+            .times(1)
+            .returning(|_, _, _| Ok("Merge\n".to_string()));
+
+        assert_eq!(get_strategy(&command, false), Strategy::Merge);
+    }
 
-        // Mock refresh_base - pull
+    fn expect_skip_checks(command: &mut MockCmd, branch: &'static str, counts: &'static str) {
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
+            .withf(|args, _, _| args == ["config", "--get", "lgit.protected"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| {
+                args == ["config", "--get", &format!("branch.{branch}.lgitStrategy")]
             })
             .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(move |args, _, _| {
+                args.starts_with(&["rev-list", "--left-right", "--count"])
+                    && args[3] == format!("refs/heads/{branch}...origin/main")
+            })
+            .times(1)
+            .returning(move |_, _, _| Ok(counts.to_string()));
+    }
+
+    fn queue(pending: &[&str]) -> Queue {
+        Queue {
+            onto: "origin/main".to_string(),
+            original: "feature".to_string(),
+            pending: pending.iter().map(|branch| branch.to_string()).collect(),
+        }
+    }
 
-        // Mock checkout back to original branch
+    #[test]
+    fn test_rebase_queue() {
+        let mut command = MockCmd::new();
+        expect_skip_checks(&mut command, "done", "0\t4\n");
+        expect_skip_checks(&mut command, "feature", "2\t3\n");
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "-"] && !(*verbose) && !(*inherit_stderr)
+                args == ["rebase", "origin/main", "feature"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.protected"])
+            .times(1)
+            .returning(|_, _, _| Ok("main,release*\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "config" && args[1] == "--unset")
+            .times(3)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["checkout", "feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
 
-        // Mock rebase - failure
+        let mut report = Report::default();
+        let result = rebase_queue(
+            &command,
+            &queue(&["done", "feature", "release-1"]),
+            &mut report,
+            false,
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            report,
+            Report {
+                rebased: vec!["feature".to_string()],
+                skipped: vec![
+                    "done (merged)".to_string(),
+                    "release-1 (protected)".to_string()
+                ],
+                conflicted: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_rebase_queue_saves_remaining_branches_on_conflict() {
+        let mut command = MockCmd::new();
+        expect_skip_checks(&mut command, "a", "1\t1\n");
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["rebase", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["rebase", "origin/main", "a"])
             .times(1)
             .returning(|_, _, _| Err(()));
+        expect_in_progress(&mut command, Some("rebase-merge"));
+        expect_config(&mut command, &["config", "lgit.resumeOnto", "origin/main"]);
+        expect_config(&mut command, &["config", "lgit.resumeBranch", "feature"]);
+        expect_config(&mut command, &["config", "lgit.resumeQueue", "b c"]);
+
+        let mut report = Report::default();
+        let result = rebase_queue(&command, &queue(&["a", "b", "c"]), &mut report, false);
+
+        assert!(result.is_err());
+        assert_eq!(report.conflicted, Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_load_queue() {
+        let mut command = MockCmd::new();
+        for (key, value) in [
+            ("lgit.resumeOnto", "origin/main\n"),
+            ("lgit.resumeBranch", "feature\n"),
+            ("lgit.resumeQueue", "b c\n"),
+        ] {
+            command
+                .expect_exec()
+                .withf(move |args, _, _| args == ["config", "--get", key])
+                .times(1)
+                .returning(move |_, _, _| Ok(value.to_string()));
+        }
+
+        assert_eq!(load_queue(&command, false), Some(queue(&["b", "c"])));
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = Report {
+            rebased: vec!["a".to_string(), "b".to_string()],
+            skipped: vec!["c (merged)".to_string()],
+            conflicted: Some("d".to_string()),
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "Rebased: a, b\nSkipped: c (merged)\nConflicted: d"
+        );
+        assert_eq!(Report::default().to_string(), "No branches to rebase");
+    }
+
+    #[test]
+    fn test_run_with_exec() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["status", "--porcelain"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        expect_config(&mut command, &["config", "--unset", "lgit.resumeStash"]);
+        expect_refresh_base(&mut command, true);
+        command
+            .expect_exec()
+            .withf(|args, _, inherit_stdio| {
+                args == ["rebase", "--exec", "cargo test", "origin/main"] && *inherit_stdio
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = run(
+            &command,
+            "main",
+            &Strategy::Rebase,
+            Some("cargo test"),
+            false,
+        );
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_run_exec_needs_rebase_strategy() {
+        let command = MockCmd::new();
 
-        let result = run(&command, "main", false);
+        let result = run(&command, "main", &Strategy::Merge, Some("make"), false);
 
         assert!(result.is_err());
     }
@@ -0,0 +1,637 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use regex::Regex;
+
+use crate::cli::{Bump, ChangelogFormat};
+use crate::commands::changelog::{self, RELEASE_PREFIX};
+use crate::commands::Exec;
+use crate::config;
+
+pub struct Options {
+    pub bump: Bump,
+    /// Pre-release identifier such as `rc`, numbered from the previous pre-release
+    pub pre: Option<String>,
+    pub dry_run: bool,
+}
+
+/// Semantic version, e.g. `1.3.0` or `1.3.0-rc.2`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Option<String>,
+}
+
+impl Version {
+    /// Parses a version or a tag naming one, such as `v1.3.0-rc.2`. Build metadata is ignored.
+    fn parse(text: &str) -> Option<Version> {
+        let text = text.trim().strip_prefix('v').unwrap_or(text.trim());
+        let text = text.split_once('+').map_or(text, |(version, _)| version);
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) if !pre.is_empty() => (core, Some(pre.to_string())),
+            Some(_) => return None,
+            None => (text, None),
+        };
+
+        let numbers: Vec<u64> = core
+            .split('.')
+            .map(|number| number.parse().ok())
+            .collect::<Option<Vec<u64>>>()?;
+
+        match numbers[..] {
+            [major, minor, patch] => Some(Version {
+                major,
+                minor,
+                patch,
+                pre,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The number of the pre-release `identifier` this version is, e.g. 2 for `rc` in `1.3.0-rc.2`
+    fn pre_number(&self, identifier: &str) -> Option<u64> {
+        let rest = self.pre.as_deref()?.strip_prefix(identifier)?;
+
+        match rest.strip_prefix('.') {
+            Some(number) => number.parse().ok(),
+            None if rest.is_empty() => Some(0),
+            None => None,
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                // A pre-release comes before the release itself
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+
+        match &self.pre {
+            Some(pre) => write!(f, "-{pre}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Compares pre-release identifiers field by field, numerically where both fields are numbers
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let fields = |pre: &str| -> Vec<String> { pre.split('.').map(str::to_string).collect() };
+
+    for (a, b) in fields(a).iter().zip(fields(b).iter()) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => a.cmp(b),
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    a.split('.').count().cmp(&b.split('.').count())
+}
+
+/// Tags the next version, worked out from the latest release and the commits since, with the
+/// changelog as the tag message. The version in `lgit.versionFile` is updated and committed first.
+pub fn run<T: Exec>(command: &T, options: &Options, verbose: bool) -> Result<(), Option<String>> {
+    let version_file = config::get(command, "lgit.versionFile", verbose);
+
+    if version_file.is_some() && !options.dry_run {
+        let status = command
+            .exec(&["status", "--porcelain"], verbose, false)
+            .map_err(|()| "Failed to check the working tree".to_string())?;
+
+        if !status.trim().is_empty() {
+            return Err(Some(
+                "Commit or stash your changes before releasing".to_string(),
+            ));
+        }
+    }
+
+    let tags = versions(command, verbose)?;
+    let latest = tags.iter().max_by(|a, b| a.1.cmp(&b.1));
+    let stable = tags
+        .iter()
+        .filter(|(_, version)| version.pre.is_none())
+        .max_by(|a, b| a.1.cmp(&b.1));
+
+    // The type of release depends on everything since the last one, including pre-releases
+    let changes = changelog::generate(command, &range(stable), verbose)?;
+
+    let bump = match options.bump {
+        Bump::Auto => detect(&changes),
+        ref bump => bump.clone(),
+    };
+
+    let version = next(
+        stable.map(|(_, version)| version),
+        latest.map(|(_, version)| version),
+        &bump,
+        options.pre.as_deref(),
+    )?;
+
+    // Keeps the prefix of existing tags, `v` for the first one
+    let prefix = match latest {
+        Some((tag, _)) if !tag.starts_with('v') => "",
+        _ => "v",
+    };
+    let tag = format!("{prefix}{version}");
+
+    // Tags on other branches aren't listed as versions, but would still stop the tag being created
+    if command
+        .exec(
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("refs/tags/{tag}"),
+            ],
+            verbose,
+            false,
+        )
+        .is_ok()
+    {
+        return Err(Some(format!(
+            "Tag '{}' already exists, it may be on another branch",
+            tag
+        )));
+    }
+
+    // A pre-release lists what changed since the previous pre-release
+    let notes = match (&options.pre, latest) {
+        (Some(_), Some(latest)) if Some(latest) != stable => {
+            changelog::generate(command, &range(Some(latest)), verbose)?
+        }
+        _ => changes,
+    };
+
+    if notes.sections.is_empty() {
+        return Err(Some(format!(
+            "No changes in {}, nothing to release",
+            notes.range
+        )));
+    }
+
+    let notes = notes.render(&ChangelogFormat::Markdown)?;
+
+    if options.dry_run {
+        println!("Next version: {} ({})\n\n{}", tag, name(&bump), notes);
+        return Ok(());
+    }
+
+    let mut updated = Vec::new();
+
+    if let Some(file) = &version_file {
+        updated = update_version_file(command, file, &version, verbose)?;
+
+        // A Cargo.lock is often ignored, it is then kept in step but not committed
+        let mut add = vec!["add", "--"];
+        add.extend(
+            updated
+                .iter()
+                .map(|file| file.path.as_str())
+                .filter(|path| {
+                    command
+                        .exec(&["ls-files", "--error-unmatch", "--", path], verbose, false)
+                        .is_ok()
+                }),
+        );
+
+        let committed = command
+            .exec(&add, verbose, false)
+            .map_err(|()| format!("Failed to stage '{}'", add[2..].join("', '")))
+            .and_then(|_| {
+                command
+                    .exec(
+                        &["commit", "--message", &format!("{RELEASE_PREFIX}{tag}")],
+                        verbose,
+                        false,
+                    )
+                    .map_err(|()| format!("Failed to commit the version bump in '{}'", file))
+            });
+
+        if let Err(err) = committed {
+            restore(command, &updated, verbose);
+
+            return Err(Some(err));
+        }
+    }
+
+    // Verbatim, as git would otherwise strip the Markdown headings as comments
+    command
+        .exec(
+            &[
+                "tag",
+                "--annotate",
+                "--cleanup=verbatim",
+                "--message",
+                &format!("{tag}\n\n{notes}"),
+                &tag,
+            ],
+            verbose,
+            false,
+        )
+        .map_err(|()| {
+            // Drops the version commit, the working tree was clean before it
+            let rolled_back = version_file.is_none()
+                || command
+                    .exec(&["reset", "--keep", "HEAD^"], verbose, false)
+                    .is_ok();
+
+            if rolled_back {
+                restore(command, &updated, verbose);
+            }
+
+            match rolled_back {
+                true => format!("Failed to create tag '{}'", tag),
+                false => format!(
+                    "Failed to create tag '{}' and to undo the version commit, drop it with 'git reset --keep HEAD^'",
+                    tag
+                ),
+            }
+        })?;
+
+    println!(
+        "Released {} ({}), push it with 'git push --follow-tags'",
+        tag,
+        name(&bump)
+    );
+
+    Ok(())
+}
+
+/// Tags reachable from HEAD naming a version
+fn versions<T: Exec>(command: &T, verbose: bool) -> Result<Vec<(String, Version)>, String> {
+    let output = command
+        .exec(&["tag", "--list", "--merged", "HEAD"], verbose, false)
+        .map_err(|()| "Failed to list tags".to_string())?;
+
+    Ok(output
+        .lines()
+        .filter_map(|tag| Version::parse(tag).map(|version| (tag.trim().to_string(), version)))
+        .collect())
+}
+
+fn range(tag: Option<&(String, Version)>) -> String {
+    match tag {
+        Some((tag, _)) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    }
+}
+
+/// Major for breaking changes, minor for features and patch for anything else
+fn detect(changes: &changelog::Changelog) -> Bump {
+    if changes.entries().any(|entry| entry.breaking.is_some()) {
+        Bump::Major
+    } else if changes
+        .sections
+        .iter()
+        .any(|section| section.kind == "feat")
+    {
+        Bump::Minor
+    } else {
+        Bump::Patch
+    }
+}
+
+/// The version after `stable` for `bump`, refused unless it comes after `latest` too, as a
+/// pre-release identifier sorting before the previous one (`beta` after `rc`) would
+fn next(
+    stable: Option<&Version>,
+    latest: Option<&Version>,
+    bump: &Bump,
+    pre: Option<&str>,
+) -> Result<Version, String> {
+    let base = stable.cloned().unwrap_or_default();
+
+    let mut version = match bump {
+        Bump::Major => Version {
+            major: base.major + 1,
+            ..Version::default()
+        },
+        Bump::Minor => Version {
+            major: base.major,
+            minor: base.minor + 1,
+            ..Version::default()
+        },
+        Bump::Patch | Bump::Auto => Version {
+            patch: base.patch + 1,
+            pre: None,
+            ..base
+        },
+    };
+
+    if let Some(identifier) = pre {
+        // Counts on from the previous pre-release of the same version, e.g. rc.1 to rc.2
+        let number = latest
+            .filter(|latest| {
+                (latest.major, latest.minor, latest.patch)
+                    == (version.major, version.minor, version.patch)
+            })
+            .and_then(|latest| latest.pre_number(identifier))
+            .map_or(1, |number| number + 1);
+
+        version.pre = Some(format!("{identifier}.{number}"));
+    }
+
+    match latest {
+        Some(latest) if version <= *latest => Err(format!(
+            "Version {} wouldn't come after the latest release {}, pick another bump or pre-release identifier",
+            version, latest
+        )),
+        _ => Ok(version),
+    }
+}
+
+/// A file rewritten for a release, with what it held before
+struct Updated {
+    path: String,
+    original: String,
+}
+
+/// Writes `version` to `file`, and to the `Cargo.lock` next to it or at the root of its workspace for
+/// a `Cargo.toml`
+fn update_version_file<T: Exec>(
+    command: &T,
+    file: &str,
+    version: &Version,
+    verbose: bool,
+) -> Result<Vec<Updated>, String> {
+    let root = command
+        .exec(&["rev-parse", "--show-toplevel"], verbose, false)
+        .map_err(|()| "Failed to find the repository root".to_string())?;
+    let path = Path::new(root.trim()).join(file);
+
+    let text = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read '{}': {}", path.display(), err))?;
+
+    let updated = set_version(&text, version)
+        .ok_or_else(|| format!("No version found in '{}'", path.display()))?;
+
+    fs::write(&path, updated)
+        .map_err(|err| format!("Failed to write '{}': {}", path.display(), err))?;
+
+    let mut updated = vec![Updated {
+        path: path.display().to_string(),
+        original: text,
+    }];
+
+    let lock = path
+        .parent()
+        .filter(|_| path.file_name().is_some_and(|name| name == "Cargo.toml"))
+        .into_iter()
+        .flat_map(Path::ancestors)
+        .take_while(|dir| dir.starts_with(root.trim()))
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| lock.is_file());
+
+    if let Some(lock) = lock {
+        let original = fs::read_to_string(&lock)
+            .map_err(|err| format!("Failed to read '{}': {}", lock.display(), err));
+
+        // Leaves the version file as it was rather than committing it out of step with the lock
+        match original.and_then(|original| {
+            update_cargo_lock(&path, verbose)
+                .map(|()| original)
+                .map_err(|err| format!("Failed to update '{}': {}", lock.display(), err))
+        }) {
+            Ok(original) => updated.push(Updated {
+                path: lock.display().to_string(),
+                original,
+            }),
+            Err(err) => {
+                restore(command, &updated, verbose);
+
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Unstages the files rewritten for a release that didn't happen and puts back what they held
+fn restore<T: Exec>(command: &T, updated: &[Updated], verbose: bool) {
+    let mut reset = vec!["reset", "--quiet", "--"];
+    reset.extend(updated.iter().map(|file| file.path.as_str()));
+
+    let _ = command.exec(&reset, verbose, false);
+
+    for file in updated {
+        let _ = fs::write(&file.path, &file.original);
+    }
+}
+
+/// Records the new version of the workspace packages in `Cargo.lock`, without touching dependencies
+fn update_cargo_lock(manifest: &Path, verbose: bool) -> Result<(), String> {
+    let manifest = manifest.display().to_string();
+    let args = [
+        "update",
+        "--workspace",
+        "--offline",
+        "--manifest-path",
+        &manifest,
+    ];
+
+    if verbose {
+        println!("Executing: cargo {}\n", args.join(" "));
+    }
+
+    let output = process::Command::new("cargo")
+        .args(args)
+        .output()
+        .map_err(|err| format!("Failed to run cargo: {}", err))?;
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// Replaces the first `version = "..."` (TOML) or `"version": "..."` (JSON) value in `text`
+fn set_version(text: &str, version: &Version) -> Option<String> {
+    let regex = Regex::new(r#"(?m)^(\s*"?version"?\s*[=:]\s*")[^"]*(")"#).unwrap();
+
+    regex.is_match(text).then(|| {
+        regex
+            .replace(text, format!("${{1}}{version}${{2}}"))
+            .to_string()
+    })
+}
+
+fn name(bump: &Bump) -> &'static str {
+    match bump {
+        Bump::Major => "major",
+        Bump::Minor => "minor",
+        Bump::Patch => "patch",
+        Bump::Auto => "auto",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            Version::parse("v1.3.0-rc.2+build.5"),
+            Some(Version {
+                major: 1,
+                minor: 3,
+                patch: 0,
+                pre: Some("rc.2".to_string()),
+            })
+        );
+        assert_eq!(version("0.9.2").to_string(), "0.9.2");
+        assert_eq!(Version::parse("release-2024"), None);
+        assert_eq!(Version::parse("v1.2"), None);
+        assert_eq!(Version::parse("v1.2.3-"), None);
+    }
+
+    #[test]
+    fn test_version_order() {
+        let mut versions: Vec<Version> = [
+            "1.3.0",
+            "1.3.0-rc.10",
+            "1.2.9",
+            "1.3.0-rc.2",
+            "1.3.0-beta.1",
+        ]
+        .iter()
+        .map(|text| version(text))
+        .collect();
+
+        versions.sort();
+
+        let sorted: Vec<String> = versions.iter().map(Version::to_string).collect();
+
+        assert_eq!(
+            sorted,
+            [
+                "1.2.9",
+                "1.3.0-beta.1",
+                "1.3.0-rc.2",
+                "1.3.0-rc.10",
+                "1.3.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next() {
+        let stable = version("1.2.3");
+
+        assert_eq!(
+            next(Some(&stable), Some(&stable), &Bump::Major, None)
+                .unwrap()
+                .to_string(),
+            "2.0.0"
+        );
+        assert_eq!(
+            next(Some(&stable), Some(&stable), &Bump::Minor, None)
+                .unwrap()
+                .to_string(),
+            "1.3.0"
+        );
+        assert_eq!(
+            next(Some(&stable), Some(&stable), &Bump::Patch, None)
+                .unwrap()
+                .to_string(),
+            "1.2.4"
+        );
+        assert_eq!(
+            next(None, None, &Bump::Minor, None).unwrap().to_string(),
+            "0.1.0"
+        );
+    }
+
+    #[test]
+    fn test_next_pre_release() {
+        let stable = version("1.2.3");
+        let rc = version("1.3.0-rc.2");
+
+        assert_eq!(
+            next(Some(&stable), Some(&stable), &Bump::Minor, Some("rc"))
+                .unwrap()
+                .to_string(),
+            "1.3.0-rc.1"
+        );
+        assert_eq!(
+            next(Some(&stable), Some(&rc), &Bump::Minor, Some("rc"))
+                .unwrap()
+                .to_string(),
+            "1.3.0-rc.3"
+        );
+        // beta sorts before rc, so would go backwards
+        assert_eq!(
+            next(Some(&stable), Some(&rc), &Bump::Minor, Some("beta")),
+            Err("Version 1.3.0-beta.1 wouldn't come after the latest release 1.3.0-rc.2, pick another bump or pre-release identifier".to_string())
+        );
+        assert_eq!(
+            next(Some(&stable), Some(&rc), &Bump::Patch, Some("rc")),
+            Err("Version 1.2.4-rc.1 wouldn't come after the latest release 1.3.0-rc.2, pick another bump or pre-release identifier".to_string())
+        );
+        // A breaking change since the release candidate moves on to the next major version
+        assert_eq!(
+            next(Some(&stable), Some(&rc), &Bump::Major, Some("rc"))
+                .unwrap()
+                .to_string(),
+            "2.0.0-rc.1"
+        );
+        assert_eq!(
+            next(Some(&stable), Some(&rc), &Bump::Minor, None)
+                .unwrap()
+                .to_string(),
+            "1.3.0"
+        );
+    }
+
+    #[test]
+    fn test_set_version() {
+        let cargo = "[package]\nname = \"lgit\"\nversion = \"0.9.2\"\n\n[dependencies]\nclap = { version = \"4.5.13\" }\n";
+
+        assert_eq!(
+            set_version(cargo, &version("0.10.0")),
+            Some("[package]\nname = \"lgit\"\nversion = \"0.10.0\"\n\n[dependencies]\nclap = { version = \"4.5.13\" }\n".to_string())
+        );
+        assert_eq!(
+            set_version(
+                "{\n  \"name\": \"app\",\n  \"version\": \"1.0.0\"\n}\n",
+                &version("1.1.0-rc.1")
+            ),
+            Some("{\n  \"name\": \"app\",\n  \"version\": \"1.1.0-rc.1\"\n}\n".to_string())
+        );
+        assert_eq!(set_version("name = \"lgit\"\n", &version("1.0.0")), None);
+    }
+}
@@ -0,0 +1,494 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, FuzzySelect, Input};
+
+use crate::cli::StashAction;
+use crate::commands::checkout::AUTOSTASH_PREFIX;
+use crate::commands::rebase::RESUME_STASH;
+use crate::commands::{git_fallback, Exec};
+use crate::config;
+use crate::parser::{self, Stash};
+use crate::utils::{format_age, now, ref_exists, STASH_PREFIX};
+
+/// What to do with the stash picked in the manager
+#[derive(Debug, PartialEq)]
+enum Action {
+    Apply,
+    Pop,
+    Drop,
+    Rename(String),
+    Branch(String),
+}
+
+const ACTIONS: [&str; 6] = [
+    "Apply",
+    "Pop",
+    "Drop",
+    "Rename",
+    "Create a branch from it",
+    "Cancel",
+];
+
+pub fn run<T: Exec>(
+    command: &T,
+    action: Option<StashAction>,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    match action {
+        Some(StashAction::Recover) => {
+            println!("{}", recover(command, verbose)?);
+
+            Ok(())
+        }
+        Some(StashAction::External(args)) => {
+            let args: Vec<String> = ["stash".to_string()].into_iter().chain(args).collect();
+
+            git_fallback::run(command, &args, verbose)
+        }
+        None => manage(command, verbose),
+    }
+}
+
+/// Picks a stash, previews its changes and applies the chosen action to it
+fn manage<T: Exec>(command: &T, verbose: bool) -> Result<(), Option<String>> {
+    let stashes =
+        parser::stashes(command, verbose).map_err(|()| "Failed to list stashes".to_string())?;
+
+    if stashes.is_empty() {
+        println!("No stashes");
+        return Ok(());
+    }
+
+    let items: Vec<String> = stashes
+        .iter()
+        .map(|stash| describe(stash, count_files(command, stash, verbose), now()))
+        .collect();
+
+    let theme = ColorfulTheme::default();
+
+    let option = FuzzySelect::with_theme(&theme)
+        .with_prompt("Which stash?")
+        .default(0)
+        .items(&items)
+        .interact()
+        .map_err(|err| {
+            if verbose {
+                println!("{err}");
+            }
+
+            format!("Failed to select stash: {}", err)
+        })?;
+
+    let stash = &stashes[option];
+
+    // Shown through git so that the diff is colored and paged as usual
+    let _ = command.exec(
+        &[
+            "stash",
+            "show",
+            "--stat",
+            "--patch",
+            "--include-untracked",
+            &stash.reference,
+        ],
+        verbose,
+        true,
+    );
+
+    let Some(action) = prompt_action(stash, &theme)? else {
+        return Ok(());
+    };
+
+    println!("{}", perform(command, stash, &action, verbose)?);
+
+    Ok(())
+}
+
+fn prompt_action(stash: &Stash, theme: &ColorfulTheme) -> Result<Option<Action>, String> {
+    let option = FuzzySelect::with_theme(theme)
+        .with_prompt(format!("What to do with {}?", stash.reference))
+        .default(0)
+        .items(ACTIONS)
+        .interact()
+        .map_err(|err| format!("Failed to select action: {}", err))?;
+
+    let action = match option {
+        0 => Action::Apply,
+        1 => Action::Pop,
+        2 => {
+            let confirmed = Confirm::with_theme(theme)
+                .with_prompt(format!("Drop {} for good?", stash.reference))
+                .default(false)
+                .interact()
+                .map_err(|err| format!("Failed to confirm drop: {}", err))?;
+
+            if !confirmed {
+                return Ok(None);
+            }
+
+            Action::Drop
+        }
+        3 => Action::Rename(
+            Input::with_theme(theme)
+                .with_prompt("New message")
+                .with_initial_text(stash.message())
+                .interact_text()
+                .map_err(|err| format!("Failed to read message: {}", err))?,
+        ),
+        4 => Action::Branch(
+            Input::with_theme(theme)
+                .with_prompt("Branch name")
+                .interact_text()
+                .map_err(|err| format!("Failed to read branch name: {}", err))?,
+        ),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(action))
+}
+
+fn perform<T: Exec>(
+    command: &T,
+    stash: &Stash,
+    action: &Action,
+    verbose: bool,
+) -> Result<String, String> {
+    let reference = stash.reference.as_str();
+
+    match action {
+        Action::Apply => {
+            command
+                .exec(&["stash", "apply", reference], verbose, false)
+                .map_err(|()| format!("Failed to apply {}, resolve the conflicts", reference))?;
+
+            Ok(format!("Applied {reference}"))
+        }
+        Action::Pop => {
+            command.exec(&["stash", "pop", reference], verbose, false).map_err(|()| {
+                format!(
+                    "Failed to pop {}, it is kept. Resolve the conflicts and drop it with 'git stash drop {}'",
+                    reference, reference
+                )
+            })?;
+
+            Ok(format!("Popped {reference}"))
+        }
+        Action::Drop => {
+            command
+                .exec(&["stash", "drop", reference], verbose, false)
+                .map_err(|()| format!("Failed to drop {}", reference))?;
+
+            Ok(format!("Dropped {reference} ({})", short(&stash.sha)))
+        }
+        Action::Rename(message) => {
+            // git can't rename a stash, so it is dropped and stored again under the new message.
+            // The stash commit outlives the drop, so it can be stored right after.
+            command
+                .exec(&["stash", "drop", reference], verbose, false)
+                .map_err(|()| format!("Failed to rename {}", reference))?;
+
+            // Keeps the branch prefix git gives stash messages, which the list shows
+            let subject = match stash.branch() {
+                "" => message.to_string(),
+                branch => format!("On {}: {}", branch, message),
+            };
+
+            command
+                .exec(
+                    &["stash", "store", "--message", &subject, &stash.sha],
+                    verbose,
+                    false,
+                )
+                .map_err(|()| {
+                    format!(
+                        "Failed to store the renamed stash, restore it with 'git stash store {}'",
+                        stash.sha
+                    )
+                })?;
+
+            Ok(format!("Renamed {} to stash@{{0}}: {}", reference, message))
+        }
+        Action::Branch(name) => {
+            command
+                .exec(&["stash", "branch", name, reference], verbose, false)
+                .map_err(|()| format!("Failed to create branch '{}' from {}", name, reference))?;
+
+            Ok(format!("Created branch {name} from {reference}"))
+        }
+    }
+}
+
+/// Number of files changed in a stash, including untracked ones
+fn count_files<T: Exec>(command: &T, stash: &Stash, verbose: bool) -> Option<usize> {
+    command
+        .exec(
+            &[
+                "stash",
+                "show",
+                "--name-only",
+                "--include-untracked",
+                &stash.reference,
+            ],
+            verbose,
+            false,
+        )
+        .ok()
+        .map(|files| files.lines().filter(|file| !file.is_empty()).count())
+}
+
+fn describe(stash: &Stash, files: Option<usize>, now: u64) -> String {
+    let files = match files {
+        Some(1) => "1 file".to_string(),
+        Some(files) => format!("{files} files"),
+        None => "? files".to_string(),
+    };
+
+    format!(
+        "{}  {}  {}  {}  {}",
+        stash.reference,
+        stash.branch(),
+        format_age(now.saturating_sub(stash.timestamp)),
+        files,
+        stash.message()
+    )
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// Lists the stashes lgit created but never restored, e.g. because popping them conflicted
+fn recover<T: Exec>(command: &T, verbose: bool) -> Result<String, String> {
+    let stashes =
+        parser::stashes(command, verbose).map_err(|()| "Failed to list stashes".to_string())?;
+
+    // Restored once the rebase or merge in progress is continued or aborted
+    let pending = config::get(command, RESUME_STASH, verbose);
+
+    let orphaned: Vec<&Stash> = stashes
+        .iter()
+        .filter(|stash| is_orphaned(command, stash, pending.as_deref(), verbose))
+        .collect();
+
+    if orphaned.is_empty() {
+        return Ok("No orphaned lgit stashes".to_string());
+    }
+
+    let mut lines: Vec<String> = orphaned
+        .iter()
+        .map(|stash| {
+            format!(
+                "{}  {}  {}",
+                stash.reference,
+                short(&stash.sha),
+                stash.subject
+            )
+        })
+        .collect();
+
+    lines.push(String::new());
+    lines.push(
+        "Restore one with 'git stash pop <stash>' or drop it with 'git stash drop <stash>'"
+            .to_string(),
+    );
+
+    Ok(lines.join("\n"))
+}
+
+fn is_orphaned<T: Exec>(command: &T, stash: &Stash, pending: Option<&str>, verbose: bool) -> bool {
+    let message = stash.message();
+
+    if message.starts_with(STASH_PREFIX) {
+        return pending != Some(stash.sha.as_str());
+    }
+
+    // Changes parked by `checkout --autostash` come back when their branch is checked out again,
+    // which can't happen anymore once it is deleted
+    match message.strip_prefix(AUTOSTASH_PREFIX) {
+        Some(branch) => !ref_exists(command, &format!("refs/heads/{branch}"), verbose),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn expect_stashes(command: &mut MockCmd, output: &'static str) {
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args.starts_with(&["stash", "list", "-z"]) && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| Ok(output.to_string()));
+    }
+
+    fn expect_resume_stash(command: &mut MockCmd, sha: Option<&'static str>) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.resumeStash"])
+            .times(1)
+            .returning(move |_, _, _| sha.map(|sha| format!("{sha}\n")).ok_or(()));
+    }
+
+    fn stash() -> Stash {
+        Stash {
+            reference: "stash@{1}".to_string(),
+            sha: "abc1234567".to_string(),
+            timestamp: 1_760_882_700,
+            subject: "On feature: work in progress".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(
+            describe(&stash(), Some(3), 1_760_882_700 + 7_200),
+            "stash@{1}  feature  2h  3 files  work in progress"
+        );
+        assert_eq!(
+            describe(&stash(), Some(1), 1_760_882_700),
+            "stash@{1}  feature  0m  1 file  work in progress"
+        );
+    }
+
+    #[test]
+    fn test_count_files() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == [
+                    "stash",
+                    "show",
+                    "--name-only",
+                    "--include-untracked",
+                    "stash@{1}",
+                ] && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("src/main.rs\nnotes.txt\n".to_string()));
+
+        assert_eq!(count_files(&command, &stash(), false), Some(2));
+    }
+
+    #[test]
+    fn test_perform_pop_conflict_keeps_stash() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{1}"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert_eq!(
+            perform(&command, &stash(), &Action::Pop, false),
+            Err("Failed to pop stash@{1}, it is kept. Resolve the conflicts and drop it with 'git stash drop stash@{1}'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_perform_rename() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "drop", "stash@{1}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "stash",
+                    "store",
+                    "--message",
+                    "On feature: login form",
+                    "abc1234567",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            perform(
+                &command,
+                &stash(),
+                &Action::Rename("login form".to_string()),
+                false
+            ),
+            Ok("Renamed stash@{1} to stash@{0}: login form".to_string())
+        );
+    }
+
+    #[test]
+    fn test_perform_branch() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "branch", "wip", "stash@{1}"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            perform(
+                &command,
+                &stash(),
+                &Action::Branch("wip".to_string()),
+                false
+            ),
+            Ok("Created branch wip from stash@{1}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recover() {
+        let mut command = MockCmd::new();
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc1234567\x001760882700\0On main: lgit-stash:rebase 2026-10-19 14:05 UTC\0\
+             stash@{1}\0def4567890\x001760882700\0On feature: lgit-stash:update 2026-10-18 09:30 UTC\0\
+             stash@{2}\0fed7890123\x001760882700\0On gone: lgit-autostash:gone\0\
+             stash@{3}\0bcd2345678\x001760882700\0On kept: lgit-autostash:kept\0\
+             stash@{4}\0cde3456789\x001760882700\0WIP on main: 1234567 Work\0",
+        );
+        expect_resume_stash(&mut command, Some("def4567890"));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/gone"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/kept"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(
+            recover(&command, false),
+            Ok(
+                "stash@{0}  abc1234  On main: lgit-stash:rebase 2026-10-19 14:05 UTC\n\
+                stash@{2}  fed7890  On gone: lgit-autostash:gone\n\
+                \n\
+                Restore one with 'git stash pop <stash>' or drop it with 'git stash drop <stash>'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_recover_nothing_orphaned() {
+        let mut command = MockCmd::new();
+        expect_stashes(
+            &mut command,
+            "stash@{0}\0abc1234567\x001760882700\0WIP on main: 1234567 Work\0",
+        );
+        expect_resume_stash(&mut command, None);
+
+        assert_eq!(
+            recover(&command, false),
+            Ok("No orphaned lgit stashes".to_string())
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::commands::{rebase, Exec};
+use crate::parser::{self, Branch};
+use crate::utils::{get_current_branch, get_recorded_base};
+
+/// Fetches all remotes and fast-forwards every local branch that is strictly behind its upstream,
+/// reporting the branches that can't be updated that way
+pub fn run<T: Exec>(command: &T, rebase: bool, verbose: bool) -> Result<(), Option<String>> {
+    command
+        .exec(&["fetch", "--all", "--prune"], verbose, false)
+        .map_err(|()| "Failed to fetch remotes (check network connection)".to_string())?;
+
+    let branches = parser::branches(command, &["refs/heads"], verbose)
+        .map_err(|()| "Failed to list local branches".to_string())?;
+
+    let report = sync_branches(command, &branches, verbose);
+
+    if report.is_empty() {
+        println!("All branches are up to date");
+    } else {
+        println!("{}", report.join("\n"));
+    }
+
+    if rebase {
+        rebase_current(command, verbose)?;
+    }
+
+    Ok(())
+}
+
+fn sync_branches<T: Exec>(command: &T, branches: &[Branch], verbose: bool) -> Vec<String> {
+    let mut report = Vec::new();
+
+    for branch in branches {
+        let Some(upstream) = &branch.upstream else {
+            continue;
+        };
+
+        let name = branch.name();
+        let upstream_name = upstream.strip_prefix("refs/remotes/").unwrap_or(upstream);
+
+        if branch.track.gone {
+            report.push(format!("Upstream of {name} is gone ({upstream_name})"));
+            continue;
+        }
+
+        match (branch.track.ahead, branch.track.behind) {
+            (_, 0) => {}
+            (0, behind) => match fast_forward(command, branch, upstream, verbose) {
+                Ok(()) => report.push(format!(
+                    "Fast-forwarded {name} to {upstream_name} ({behind} commits)"
+                )),
+                Err(()) => report.push(format!(
+                    "Failed to fast-forward {name} to {upstream_name} (checked out elsewhere or conflicting changes?)"
+                )),
+            },
+            (ahead, behind) => report.push(format!(
+                "Diverged {name} from {upstream_name} ({ahead} ahead, {behind} behind)"
+            )),
+        }
+    }
+
+    report
+}
+
+/// Fast-forwards `branch` to `upstream` without checking it out, which git refuses for branches
+/// checked out in another worktree. Only the current branch is merged into.
+fn fast_forward<T: Exec>(
+    command: &T,
+    branch: &Branch,
+    upstream: &str,
+    verbose: bool,
+) -> Result<(), ()> {
+    if branch.head {
+        command.exec(&["merge", "--ff-only", "--quiet", upstream], verbose, false)?;
+    } else {
+        command.exec(
+            &["fetch", ".", &format!("{upstream}:{}", branch.refname)],
+            verbose,
+            false,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Updates the current branch with the base it was created from with `lgit branch`, using its
+/// configured strategy
+fn rebase_current<T: Exec>(command: &T, verbose: bool) -> Result<(), Option<String>> {
+    let Some(current) = get_current_branch(command, verbose) else {
+        println!("Not rebasing, HEAD is not on a branch");
+        return Ok(());
+    };
+
+    let Some(base) = get_recorded_base(command, &current, verbose) else {
+        println!("Not rebasing {current}, it has no recorded base (branches created with 'lgit branch' have one)");
+        return Ok(());
+    };
+
+    rebase::run(
+        command,
+        &base,
+        &rebase::get_strategy(command, verbose),
+        None,
+        verbose,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    const BRANCHES: &str = "*\0refs/heads/main\0refs/remotes/origin/main\0[behind 2]\x001700000000\0Jane <jane@example.com>\0\n \
+        \0refs/heads/feature\0refs/remotes/origin/feature\0[behind 1]\x001700000000\0Jane <jane@example.com>\0\n \
+        \0refs/heads/diverged\0refs/remotes/origin/diverged\0[ahead 1, behind 3]\x001700000000\0Jane <jane@example.com>\0\n \
+        \0refs/heads/ahead\0refs/remotes/origin/ahead\0[ahead 4]\x001700000000\0Jane <jane@example.com>\0\n \
+        \0refs/heads/old\0refs/remotes/origin/old\0[gone]\x001600000000\0John <john@example.com>\0\n \
+        \0refs/heads/local\0\0\x001600000000\0John <john@example.com>\0\n";
+
+    fn parser_branches(output: &'static str) -> Vec<Branch> {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .returning(move |_, _, _| Ok(output.to_string()));
+
+        parser::branches(&command, &["refs/heads"], false).unwrap()
+    }
+
+    #[test]
+    fn test_sync_branches() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["merge", "--ff-only", "--quiet", "refs/remotes/origin/main"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "fetch",
+                    ".",
+                    "refs/remotes/origin/feature:refs/heads/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let branches = parser_branches(BRANCHES);
+        let report = sync_branches(&command, &branches, false);
+
+        assert_eq!(
+            report,
+            vec![
+                "Fast-forwarded main to origin/main (2 commits)",
+                "Failed to fast-forward feature to origin/feature (checked out elsewhere or conflicting changes?)",
+                "Diverged diverged from origin/diverged (1 ahead, 3 behind)",
+                "Upstream of old is gone (origin/old)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_rebase_without_recorded_base() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["fetch", "--all", "--prune"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "for-each-ref")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.lgitBase"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert_eq!(run(&command, true, false), Ok(()));
+    }
+
+    #[test]
+    fn test_run_fetch_failure() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["fetch", "--all", "--prune"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert!(run(&command, false, false).is_err());
+    }
+}
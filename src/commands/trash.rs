@@ -0,0 +1,508 @@
+use crate::cli::TrashAction;
+use crate::commands::Exec;
+use crate::config;
+use crate::utils::{days_to_seconds, format_date, now, parse_days};
+
+const TRASH_PREFIX: &str = "refs/lgit/trash/";
+const TRASH_SECTION: &str = "lgit-trash";
+
+#[derive(Debug, PartialEq)]
+struct Entry {
+    date: String,
+    /// Tells apart branches of the same name deleted on the same day, 1 for the first
+    slot: u32,
+    name: String,
+    sha: String,
+    subject: String,
+}
+
+impl Entry {
+    /// The date of the deletion, followed by the slot from the second one of the day on,
+    /// e.g. `2026-02-01` or `2026-02-01.2`
+    fn stamp(&self) -> String {
+        match self.slot {
+            1 => self.date.clone(),
+            slot => format!("{}.{slot}", self.date),
+        }
+    }
+
+    fn refname(&self) -> String {
+        format!("{TRASH_PREFIX}{}/{}", self.stamp(), self.name)
+    }
+
+    fn section(&self) -> String {
+        format!("{TRASH_SECTION}.{}/{}", self.stamp(), self.name)
+    }
+}
+
+pub fn run<T: Exec>(command: &T, action: TrashAction, verbose: bool) -> Result<(), Option<String>> {
+    let output = match action {
+        TrashAction::List => list(command, verbose),
+        TrashAction::Restore { name } => restore(command, &name, verbose),
+        TrashAction::Purge { older_than } => purge(command, &older_than, verbose),
+    }?;
+
+    println!("{output}");
+
+    Ok(())
+}
+
+/// Saves the tip and upstream configuration of a local branch under `refs/lgit/trash/<date>/<name>`
+/// so it can be brought back with `lgit trash restore` after it has been deleted. Deleting a branch
+/// of the same name again on the same day takes the next slot, e.g. `<date>.2/<name>`.
+pub fn backup<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<String, String> {
+    let sha = command
+        .exec(
+            &["rev-parse", "--verify", &format!("refs/heads/{branch}")],
+            verbose,
+            false,
+        )
+        .map_err(|()| format!("Failed to resolve branch '{}'", branch))?;
+
+    let mut entry = Entry {
+        date: format_date(now()),
+        slot: 1,
+        name: branch.to_string(),
+        sha: sha.trim().to_string(),
+        subject: String::new(),
+    };
+
+    // The empty old value makes git refuse to overwrite an earlier backup in the same slot
+    while command
+        .exec(
+            &["update-ref", &entry.refname(), &entry.sha, ""],
+            verbose,
+            false,
+        )
+        .is_err()
+    {
+        let taken = command
+            .exec(
+                &["rev-parse", "--verify", "--quiet", &entry.refname()],
+                verbose,
+                false,
+            )
+            .is_ok();
+
+        if !taken {
+            return Err(format!("Failed to back up branch '{}'", branch));
+        }
+
+        entry.slot += 1;
+    }
+
+    for key in ["remote", "merge"] {
+        let Some(value) = config::get(command, &format!("branch.{branch}.{key}"), verbose) else {
+            continue;
+        };
+
+        command
+            .exec(
+                &["config", &format!("{}.{key}", entry.section()), &value],
+                verbose,
+                false,
+            )
+            .map_err(|()| format!("Failed to back up upstream of branch '{}'", branch))?;
+    }
+
+    Ok(entry.sha)
+}
+
+fn get_entries<T: Exec>(command: &T, verbose: bool) -> Result<Vec<Entry>, String> {
+    let output = command
+        .exec(
+            &[
+                "for-each-ref",
+                "--format=%(refname)%00%(objectname:short)%00%(subject)",
+                TRASH_PREFIX,
+            ],
+            verbose,
+            false,
+        )
+        .map_err(|()| "Failed to list deleted branches".to_string())?;
+
+    let mut entries: Vec<Entry> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let refname = fields.next()?.strip_prefix(TRASH_PREFIX)?;
+            let (stamp, name) = refname.split_once('/')?;
+            let (date, slot) = match stamp.split_once('.') {
+                Some((date, slot)) => (date, slot.parse().ok()?),
+                None => (stamp, 1),
+            };
+
+            Some(Entry {
+                date: date.to_string(),
+                slot,
+                name: name.to_string(),
+                sha: fields.next()?.to_string(),
+                subject: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+
+    // Most recently deleted first
+    entries.sort_by(|a, b| {
+        (&b.date, b.slot)
+            .cmp(&(&a.date, a.slot))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(entries)
+}
+
+fn list<T: Exec>(command: &T, verbose: bool) -> Result<String, String> {
+    let entries = get_entries(command, verbose)?;
+
+    if entries.is_empty() {
+        return Ok("No deleted branches".to_string());
+    }
+
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}  {}  {} {}",
+                entry.stamp(),
+                entry.name,
+                entry.sha,
+                entry.subject
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn restore<T: Exec>(command: &T, name: &str, verbose: bool) -> Result<String, String> {
+    let entries = get_entries(command, verbose)?;
+
+    // Accept both `<name>` (latest deletion wins) and `<date>/<name>`
+    let entry = entries
+        .iter()
+        .find(|entry| entry.name == name || format!("{}/{}", entry.stamp(), entry.name) == name)
+        .ok_or_else(|| format!("No deleted branch named '{}' (see 'lgit trash list')", name))?;
+
+    command
+        .exec(&["branch", &entry.name, &entry.sha], verbose, false)
+        .map_err(|()| {
+            format!(
+                "Failed to restore branch '{}' (does it already exist?)",
+                entry.name
+            )
+        })?;
+
+    for key in ["remote", "merge"] {
+        if let Some(value) = config::get(command, &format!("{}.{key}", entry.section()), verbose) {
+            command
+                .exec(
+                    &["config", &format!("branch.{}.{key}", entry.name), &value],
+                    verbose,
+                    false,
+                )
+                .map_err(|()| format!("Failed to restore upstream of branch '{}'", entry.name))?;
+        }
+    }
+
+    remove(command, entry, verbose)?;
+
+    Ok(format!("Restored branch {} at {}", entry.name, entry.sha))
+}
+
+fn purge<T: Exec>(command: &T, older_than: &str, verbose: bool) -> Result<String, String> {
+    let days = parse_days(older_than)?;
+    let cutoff = format_date(now().saturating_sub(days_to_seconds(days)));
+
+    let mut result = Vec::new();
+
+    for entry in get_entries(command, verbose)? {
+        if entry.date >= cutoff {
+            continue;
+        }
+
+        remove(command, &entry, verbose)?;
+
+        result.push(format!("Purged {}/{}", entry.stamp(), entry.name));
+    }
+
+    Ok(if result.is_empty() {
+        format!("No deleted branches older than {older_than}")
+    } else {
+        result.join("\n")
+    })
+}
+
+fn remove<T: Exec>(command: &T, entry: &Entry, verbose: bool) -> Result<(), String> {
+    command
+        .exec(&["update-ref", "-d", &entry.refname()], verbose, false)
+        .map_err(|()| format!("Failed to remove backup of branch '{}'", entry.name))?;
+
+    // The section only exists if the branch had an upstream
+    let _ = command.exec(
+        &["config", "--remove-section", &entry.section()],
+        verbose,
+        false,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    fn cmd_trash_entries(output: &'static str) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args[0] == "for-each-ref"
+                    && args[2] == TRASH_PREFIX
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| Ok(output.to_string()));
+
+        command
+    }
+
+    #[test]
+    fn test_backup_with_upstream() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "refs/heads/feature"])
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args[0] == "update-ref"
+                    && args[1].starts_with(TRASH_PREFIX)
+                    && args[1].ends_with("/feature")
+                    && args[2..] == ["abc123", ""]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.remote"])
+            .times(1)
+            .returning(|_, _, _| Ok("origin\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.merge"])
+            .times(1)
+            .returning(|_, _, _| Ok("refs/heads/feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args.len() == 3
+                    && args[0] == "config"
+                    && args[1].starts_with("lgit-trash.")
+                    && args[1].ends_with("/feature.remote")
+                    && args[2] == "origin"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args.len() == 3
+                    && args[0] == "config"
+                    && args[1].ends_with("/feature.merge")
+                    && args[2] == "refs/heads/feature"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = backup(&command, "feature", false);
+
+        assert_eq!(result, Ok("abc123".to_string()));
+    }
+
+    #[test]
+    fn test_backup_takes_next_slot() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "rev-parse" && args[1] == "--verify" && args.len() == 3)
+            .times(1)
+            .returning(|_, _, _| Ok("abc123\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "update-ref" && !args[1].contains('.'))
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[..3] == ["rev-parse", "--verify", "--quiet"])
+            .times(1)
+            .returning(|_, _, _| Ok("def456\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args[0] == "update-ref" && args[1].ends_with(".2/feature") && args[3].is_empty()
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "config")
+            .times(2)
+            .returning(|_, _, _| Err(()));
+
+        let result = backup(&command, "feature", false);
+
+        assert_eq!(result, Ok("abc123".to_string()));
+    }
+
+    #[test]
+    fn test_list_sorts_most_recent_first() {
+        let command = cmd_trash_entries(
+            "refs/lgit/trash/2026-01-01/old\0aaa111\0Old work\nrefs/lgit/trash/2026-02-01/feature/x\0bbb222\0New work\n",
+        );
+
+        let result = list(&command, false);
+
+        assert_eq!(
+            result,
+            Ok(
+                "2026-02-01  feature/x  bbb222 New work\n2026-01-01  old  aaa111 Old work"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_restore_latest_slot_of_the_day() {
+        let mut command = cmd_trash_entries(
+            "refs/lgit/trash/2026-02-01/feature\0aaa111\0First\nrefs/lgit/trash/2026-02-01.2/feature\0bbb222\0Second\n",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["branch", "feature", "bbb222"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[..2] == ["config", "--get"])
+            .times(2)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == ["update-ref", "-d", "refs/lgit/trash/2026-02-01.2/feature"]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "config",
+                    "--remove-section",
+                    "lgit-trash.2026-02-01.2/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let result = restore(&command, "feature", false);
+
+        assert_eq!(result, Ok("Restored branch feature at bbb222".to_string()));
+    }
+
+    #[test]
+    fn test_list_empty() {
+        let command = cmd_trash_entries("");
+
+        assert_eq!(list(&command, false), Ok("No deleted branches".to_string()));
+    }
+
+    #[test]
+    fn test_restore_latest_entry() {
+        let mut command = cmd_trash_entries(
+            "refs/lgit/trash/2026-01-01/feature\0aaa111\0Old\nrefs/lgit/trash/2026-02-01/feature\0bbb222\0New\n",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["branch", "feature", "bbb222"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit-trash.2026-02-01/feature.remote"])
+            .times(1)
+            .returning(|_, _, _| Ok("origin\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit-trash.2026-02-01/feature.merge"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "branch.feature.remote", "origin"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["update-ref", "-d", "refs/lgit/trash/2026-02-01/feature"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "config",
+                    "--remove-section",
+                    "lgit-trash.2026-02-01/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = restore(&command, "feature", false);
+
+        assert_eq!(result, Ok("Restored branch feature at bbb222".to_string()));
+    }
+
+    #[test]
+    fn test_restore_unknown_branch() {
+        let command = cmd_trash_entries("refs/lgit/trash/2026-01-01/feature\0aaa111\0Old\n");
+
+        let result = restore(&command, "missing", false);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_purge_only_old_entries() {
+        let mut command = cmd_trash_entries(
+            "refs/lgit/trash/2000-01-01/old\0aaa111\0Old\nrefs/lgit/trash/2999-01-01/new\0bbb222\0New\n",
+        );
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["update-ref", "-d", "refs/lgit/trash/2000-01-01/old"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--remove-section", "lgit-trash.2000-01-01/old"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let result = purge(&command, "30d", false);
+
+        assert_eq!(result, Ok("Purged 2000-01-01/old".to_string()));
+    }
+
+    #[test]
+    fn test_purge_invalid_age() {
+        let command = MockCmd::new();
+
+        assert!(purge(&command, "soon", false).is_err());
+    }
+}
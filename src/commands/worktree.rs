@@ -0,0 +1,374 @@
+use std::path::{Component, Path, PathBuf};
+
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::FuzzySelect;
+
+use crate::cli::WorktreeAction;
+use crate::commands::Exec;
+use crate::config;
+use crate::parser::{self, Worktree};
+use crate::utils::ref_exists;
+
+/// Where new worktrees are added unless `lgit.worktreePath` says otherwise, relative to the main
+/// worktree
+const DEFAULT_PATH: &str = "../{repo}.worktrees/{branch}";
+
+pub fn run<T: Exec>(
+    command: &T,
+    action: WorktreeAction,
+    verbose: bool,
+) -> Result<(), Option<String>> {
+    let output = match action {
+        WorktreeAction::List => list(command, verbose),
+        WorktreeAction::Prune => prune(command, verbose),
+        WorktreeAction::Remove { name, force } => remove(command, name, force, verbose),
+    }?;
+
+    println!("{output}");
+
+    Ok(())
+}
+
+/// Returns the path of the worktree `branch` is checked out in, adding a worktree for it first if
+/// there is none. A missing local branch is created from the remote-tracking branch `track`.
+pub fn open<T: Exec>(
+    command: &T,
+    branch: &str,
+    track: Option<&str>,
+    verbose: bool,
+) -> Result<String, String> {
+    let worktrees = get_worktrees(command, verbose)?;
+
+    if let Some(worktree) = worktrees
+        .iter()
+        .find(|worktree| worktree.branch.as_deref() == Some(branch))
+    {
+        return Ok(worktree.path.clone());
+    }
+
+    let main = worktrees
+        .first()
+        .ok_or_else(|| "Failed to find the main worktree".to_string())?;
+
+    let template = config::get(command, "lgit.worktreePath", verbose)
+        .unwrap_or_else(|| DEFAULT_PATH.to_string());
+    let path = worktree_path(&main.path, &template, branch);
+
+    let args = match track {
+        Some(remote_branch) if !ref_exists(command, &format!("refs/heads/{branch}"), verbose) => {
+            vec![
+                "worktree",
+                "add",
+                "--track",
+                "-b",
+                branch,
+                &path,
+                remote_branch,
+            ]
+        }
+        _ => vec!["worktree", "add", &path, branch],
+    };
+
+    command.exec(&args, verbose, false).map_err(|()| {
+        format!(
+            "Failed to add a worktree for branch '{}' at '{}'",
+            branch, path
+        )
+    })?;
+
+    Ok(path)
+}
+
+/// Expands the `{repo}` and `{branch}` placeholders of `template` and resolves it against the main
+/// worktree
+fn worktree_path(main: &str, template: &str, branch: &str) -> String {
+    let main = Path::new(main);
+    let repo = main
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+
+    let relative = template
+        .replace("{repo}", &repo)
+        .replace("{branch}", branch);
+
+    // Resolve `..` so the printed path is the one `git worktree list` shows
+    let mut path = PathBuf::new();
+
+    for component in main.join(relative).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+
+    path.to_string_lossy().to_string()
+}
+
+fn get_worktrees<T: Exec>(command: &T, verbose: bool) -> Result<Vec<Worktree>, String> {
+    parser::worktrees(command, verbose).map_err(|()| "Failed to list worktrees".to_string())
+}
+
+fn describe(worktree: &Worktree) -> String {
+    let checkout = match (&worktree.branch, worktree.bare) {
+        (_, true) => "(bare)".to_string(),
+        (Some(branch), _) => branch.clone(),
+        (None, _) => format!(
+            "(detached at {})",
+            worktree.head.get(..7).unwrap_or(&worktree.head)
+        ),
+    };
+
+    let prunable = if worktree.prunable { "  prunable" } else { "" };
+
+    format!("{}  {}{}", worktree.path, checkout, prunable)
+}
+
+fn list<T: Exec>(command: &T, verbose: bool) -> Result<String, String> {
+    Ok(get_worktrees(command, verbose)?
+        .iter()
+        .map(describe)
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn prune<T: Exec>(command: &T, verbose: bool) -> Result<String, String> {
+    let prunable: Vec<String> = get_worktrees(command, verbose)?
+        .into_iter()
+        .filter(|worktree| worktree.prunable)
+        .map(|worktree| format!("Pruned {}", worktree.path))
+        .collect();
+
+    if prunable.is_empty() {
+        return Ok("No worktrees to prune".to_string());
+    }
+
+    command
+        .exec(&["worktree", "prune"], verbose, false)
+        .map_err(|()| "Failed to prune worktrees".to_string())?;
+
+    Ok(prunable.join("\n"))
+}
+
+fn remove<T: Exec>(
+    command: &T,
+    name: Option<String>,
+    force: bool,
+    verbose: bool,
+) -> Result<String, String> {
+    // The main worktree can't be removed
+    let worktrees: Vec<Worktree> = get_worktrees(command, verbose)?
+        .into_iter()
+        .skip(1)
+        .filter(|worktree| !worktree.bare)
+        .collect();
+
+    let worktree = match name {
+        Some(name) => worktrees
+            .iter()
+            .find(|worktree| worktree.path == name || worktree.branch.as_deref() == Some(&name))
+            .ok_or_else(|| format!("No worktree found for '{}'", name))?,
+        None => select(&worktrees, verbose)?,
+    };
+
+    let mut args = vec!["worktree", "remove"];
+
+    if force {
+        args.push("--force");
+    }
+
+    args.push(&worktree.path);
+
+    command.exec(&args, verbose, false).map_err(|()| {
+        format!(
+            "Failed to remove worktree '{}' (use --force to discard its changes)",
+            worktree.path
+        )
+    })?;
+
+    Ok(format!("Removed worktree {}", worktree.path))
+}
+
+fn select(worktrees: &[Worktree], verbose: bool) -> Result<&Worktree, String> {
+    if worktrees.is_empty() {
+        return Err("No worktrees to remove".to_string());
+    }
+
+    let items: Vec<String> = worktrees.iter().map(describe).collect();
+
+    let option = FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which worktree to remove?")
+        .default(0)
+        .items(&items)
+        .interact()
+        .map_err(|err| {
+            if verbose {
+                println!("{err}");
+            }
+
+            format!("Failed to select worktree: {}", err)
+        })?;
+
+    Ok(&worktrees[option])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    const WORKTREES: &str = "worktree /src/repo\0HEAD abc1234567\0branch refs/heads/main\0\0\
+        worktree /src/repo.worktrees/feature\0HEAD def4567890\0branch refs/heads/feature\0\0\
+        worktree /tmp/gone\0HEAD fed7890123\0detached\0prunable gitdir file points to non-existent location\0\0";
+
+    fn cmd_worktrees() -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["worktree", "list", "--porcelain", "-z"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(WORKTREES.to_string()));
+
+        command
+    }
+
+    #[test]
+    fn test_worktree_path_default_layout() {
+        assert_eq!(
+            worktree_path("/src/repo", DEFAULT_PATH, "feature/x"),
+            "/src/repo.worktrees/feature/x"
+        );
+    }
+
+    #[test]
+    fn test_worktree_path_absolute_template() {
+        assert_eq!(
+            worktree_path("/src/repo", "/work/{repo}-{branch}", "fix"),
+            "/work/repo-fix"
+        );
+    }
+
+    #[test]
+    fn test_open_reuses_existing_worktree() {
+        let command = cmd_worktrees();
+
+        let result = open(&command, "feature", None, false);
+
+        assert_eq!(result, Ok("/src/repo.worktrees/feature".to_string()));
+    }
+
+    #[test]
+    fn test_open_adds_worktree() {
+        let mut command = cmd_worktrees();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.worktreePath"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["worktree", "add", "/src/repo.worktrees/old", "old"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = open(&command, "old", None, false);
+
+        assert_eq!(result, Ok("/src/repo.worktrees/old".to_string()));
+    }
+
+    #[test]
+    fn test_open_creates_tracking_branch() {
+        let mut command = cmd_worktrees();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.worktreePath"])
+            .times(1)
+            .returning(|_, _, _| Ok("../wt/{branch}\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["show-ref", "--verify", "--quiet", "refs/heads/new"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "worktree",
+                    "add",
+                    "--track",
+                    "-b",
+                    "new",
+                    "/src/wt/new",
+                    "origin/new",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = open(&command, "new", Some("origin/new"), false);
+
+        assert_eq!(result, Ok("/src/wt/new".to_string()));
+    }
+
+    #[test]
+    fn test_list() {
+        let command = cmd_worktrees();
+
+        assert_eq!(
+            list(&command, false),
+            Ok("/src/repo  main\n/src/repo.worktrees/feature  feature\n/tmp/gone  (detached at fed7890)  prunable".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prune_reports_prunable_worktrees() {
+        let mut command = cmd_worktrees();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["worktree", "prune"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        assert_eq!(prune(&command, false), Ok("Pruned /tmp/gone".to_string()));
+    }
+
+    #[test]
+    fn test_remove_by_branch() {
+        let mut command = cmd_worktrees();
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "worktree",
+                    "remove",
+                    "--force",
+                    "/src/repo.worktrees/feature",
+                ]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+
+        let result = remove(&command, Some("feature".to_string()), true, false);
+
+        assert_eq!(
+            result,
+            Ok("Removed worktree /src/repo.worktrees/feature".to_string())
+        );
+    }
+
+    #[test]
+    fn test_remove_refuses_main_worktree() {
+        let command = cmd_worktrees();
+
+        let result = remove(&command, Some("main".to_string()), false, false);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::commands::Exec;
+
+/// Reads a single value from git config (e.g. `lgit.autostash`), `None` when it isn't set
+pub fn get<T: Exec + ?Sized>(command: &T, key: &str, verbose: bool) -> Option<String> {
+    command
+        .exec(&["config", "--get", key], verbose, false)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+/// Reads a boolean from git config, accepting every spelling git does (`true`, `yes`, `on`, `1`...)
+pub fn get_bool<T: Exec + ?Sized>(command: &T, key: &str, verbose: bool) -> Option<bool> {
+    command
+        .exec(&["config", "--type=bool", "--get", key], verbose, false)
+        .ok()
+        .map(|value| value.trim() == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    #[test]
+    fn test_get() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args == ["config", "--get", "lgit.remote"] && !(*verbose) && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(|_, _, _| Ok("upstream\n".to_string()));
+
+        assert_eq!(
+            get(&command, "lgit.remote", false),
+            Some("upstream".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_unset() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        assert_eq!(get(&command, "lgit.remote", false), None);
+    }
+
+    #[test]
+    fn test_get_bool() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.autostash"])
+            .times(1)
+            .returning(|_, _, _| Ok("false\n".to_string()));
+
+        assert_eq!(get_bool(&command, "lgit.autostash", false), Some(false));
+    }
+}
@@ -0,0 +1,500 @@
+use std::fmt;
+
+use regex::Regex;
+
+use crate::commands::Exec;
+use crate::config;
+
+/// Types allowed unless `lgit.commitTypes` lists others
+pub const DEFAULT_TYPES: &str = "feat,fix,docs,style,refactor,perf,test,build,ci,chore,revert";
+const DEFAULT_SUBJECT_MAX_LENGTH: usize = 72;
+const DEFAULT_BODY_MAX_LENGTH: usize = 100;
+
+/// First line of a Conventional Commits message, e.g. `feat(parser)!: drop the legacy format`
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl Header {
+    pub fn parse(line: &str) -> Option<Header> {
+        let regex = Regex::new(r"^(\w+)(?:\(([^()\s]+)\))?(!)?: (.*)$").unwrap();
+        let captures = regex.captures(line)?;
+
+        Some(Header {
+            kind: captures[1].to_string(),
+            scope: captures.get(2).map(|scope| scope.as_str().to_string()),
+            breaking: captures.get(3).is_some(),
+            description: captures[4].to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(scope) = &self.scope {
+            write!(f, "({scope})")?;
+        }
+
+        if self.breaking {
+            write!(f, "!")?;
+        }
+
+        write!(f, ": {}", self.description)
+    }
+}
+
+/// Limits commit messages are checked against
+pub struct Rules {
+    pub types: Vec<String>,
+    /// Whether subjects must follow Conventional Commits
+    pub conventional: bool,
+    /// Whether subjects must start with a verb in the imperative mood, as far as it can be guessed
+    pub imperative: bool,
+    /// Maximum length of the first line
+    pub subject_max_length: usize,
+    /// Maximum length of body lines, lines with a URL are exempt
+    pub body_max_length: usize,
+    /// Trailers every message needs, e.g. `Signed-off-by`
+    pub trailers: Vec<String>,
+    /// Start of the comment lines git strips, from `core.commentChar`
+    pub comment: String,
+}
+
+impl Rules {
+    /// Reads the rules from `lgit.commitTypes`, `lgit.lintConventional`, `lgit.lintImperative`,
+    /// `lgit.subjectMaxLength`, `lgit.bodyMaxLength`, `lgit.requiredTrailers` and
+    /// `core.commentChar`
+    pub fn load<T: Exec + ?Sized>(command: &T, verbose: bool) -> Rules {
+        let length = |key: &str, default: usize| {
+            config::get(command, key, verbose)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Rules {
+            types: types(command, verbose),
+            conventional: config::get_bool(command, "lgit.lintConventional", verbose)
+                .unwrap_or(true),
+            imperative: config::get_bool(command, "lgit.lintImperative", verbose).unwrap_or(true),
+            subject_max_length: length("lgit.subjectMaxLength", DEFAULT_SUBJECT_MAX_LENGTH),
+            body_max_length: length("lgit.bodyMaxLength", DEFAULT_BODY_MAX_LENGTH),
+            trailers: split_list(
+                &config::get(command, "lgit.requiredTrailers", verbose).unwrap_or_default(),
+            ),
+            // `auto` picks a character per message, `#` unless the message uses it
+            comment: config::get(command, "core.commentChar", verbose)
+                .filter(|comment| comment != "auto")
+                .unwrap_or_else(|| "#".to_string()),
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            types: split_list(DEFAULT_TYPES),
+            conventional: true,
+            imperative: true,
+            subject_max_length: DEFAULT_SUBJECT_MAX_LENGTH,
+            body_max_length: DEFAULT_BODY_MAX_LENGTH,
+            trailers: Vec::new(),
+            comment: "#".to_string(),
+        }
+    }
+}
+
+/// Commit types from `lgit.commitTypes`, in the order they are offered and listed in changelogs
+pub fn types<T: Exec + ?Sized>(command: &T, verbose: bool) -> Vec<String> {
+    split_list(
+        &config::get(command, "lgit.commitTypes", verbose)
+            .unwrap_or_else(|| DEFAULT_TYPES.to_string()),
+    )
+}
+
+/// Checks a commit message against `rules`, returning every problem found. Comment lines and
+/// everything below the scissors line of `git commit --verbose` are ignored, as git strips them
+/// before committing.
+pub fn lint(message: &str, rules: &Rules) -> Vec<String> {
+    let scissors = format!(
+        "{} ------------------------ >8 ------------------------",
+        rules.comment
+    );
+
+    let lines: Vec<&str> = message
+        .lines()
+        .take_while(|line| *line != scissors)
+        .filter(|line| !line.starts_with(&rules.comment))
+        .collect();
+
+    let Some(subject) = lines.first().filter(|subject| !subject.trim().is_empty()) else {
+        return vec!["The commit message is empty".to_string()];
+    };
+
+    // Squashed away before the branch lands, or written by git itself
+    if is_fixup(subject) || subject.starts_with("Merge ") || subject.starts_with("Revert \"") {
+        return Vec::new();
+    }
+
+    let mut problems = lint_subject(subject, rules);
+
+    if lines.get(1).is_some_and(|line| !line.trim().is_empty()) {
+        problems.push("Separate the subject from the body with a blank line".to_string());
+    }
+
+    for (number, line) in lines.iter().enumerate().skip(2) {
+        let length = line.chars().count();
+
+        if length > rules.body_max_length && !line.contains("://") {
+            problems.push(format!(
+                "Line {} is {} characters long, the limit is {}",
+                number + 1,
+                length,
+                rules.body_max_length
+            ));
+        }
+    }
+
+    let found = trailers(&lines.join("\n"));
+
+    for trailer in &rules.trailers {
+        if !found
+            .iter()
+            .any(|(key, _)| key.eq_ignore_ascii_case(trailer))
+        {
+            problems.push(format!("The '{trailer}' trailer is missing"));
+        }
+    }
+
+    problems
+}
+
+/// Checks the first line of a commit message against `rules`
+pub fn lint_subject(subject: &str, rules: &Rules) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let description = match Header::parse(subject) {
+        Some(header) => {
+            if rules.conventional && !rules.types.contains(&header.kind) {
+                problems.push(format!(
+                    "Type '{}' is not one of {}",
+                    header.kind,
+                    rules.types.join(", ")
+                ));
+            }
+
+            header.description
+        }
+        None if rules.conventional => {
+            problems.push(format!(
+                "The subject '{}' doesn't follow Conventional Commits, e.g. 'feat(scope): add something'",
+                subject
+            ));
+
+            return problems;
+        }
+        None => subject.to_string(),
+    };
+
+    if description.trim().is_empty() {
+        problems.push("The description after the type is empty".to_string());
+    } else if description.ends_with('.') {
+        problems.push("The subject should not end with a period".to_string());
+    }
+
+    if rules.imperative {
+        if let Some(word) = description.split_whitespace().next() {
+            if !is_imperative(word) {
+                problems.push(format!(
+                    "Start the subject with a verb in the imperative mood, e.g. 'fix' rather than '{}'",
+                    word
+                ));
+            }
+        }
+    }
+
+    let length = subject.chars().count();
+
+    if length > rules.subject_max_length {
+        problems.push(format!(
+            "The subject is {} characters long, the limit is {}",
+            length, rules.subject_max_length
+        ));
+    }
+
+    problems
+}
+
+/// Whether the subject marks a commit `git rebase --autosquash` folds into another one
+pub fn is_fixup(subject: &str) -> bool {
+    ["fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|prefix| subject.starts_with(prefix))
+}
+
+/// Trailers such as `Refs: #12` or `BREAKING CHANGE: ...` in the last paragraph of a message
+pub fn trailers(message: &str) -> Vec<(String, String)> {
+    let regex = Regex::new(r"^([A-Za-z][A-Za-z0-9-]*|BREAKING CHANGE): (.+)$").unwrap();
+    let paragraphs: Vec<&str> = message
+        .trim()
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .collect();
+
+    // The subject is never a trailer
+    if paragraphs.len() < 2 {
+        return Vec::new();
+    }
+
+    paragraphs[paragraphs.len() - 1]
+        .lines()
+        .filter_map(|line| regex.captures(line.trim_end()))
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect()
+}
+
+/// Guesses whether `word` is a verb in the imperative mood, flagging the `-ed`, `-ing` and `-s`
+/// forms people tend to write instead (`added`, `adding`, `adds`)
+fn is_imperative(word: &str) -> bool {
+    const EXCEPTIONS: [&str; 16] = [
+        "bring", "embed", "exceed", "feed", "need", "ping", "proceed", "seed", "shed", "speed",
+        "spring", "string", "succeed", "swing", "wing", "thing",
+    ];
+
+    let word = word.to_lowercase();
+
+    if !word.chars().all(|c| c.is_ascii_alphabetic()) || EXCEPTIONS.contains(&word.as_str()) {
+        return true;
+    }
+
+    let length = word.len();
+
+    !((length > 4 && word.ends_with("ed"))
+        || (length > 5 && word.ends_with("ing"))
+        || (length > 3
+            && word.ends_with('s')
+            && !["ss", "us", "is", "as"]
+                .iter()
+                .any(|end| word.ends_with(end))))
+}
+
+/// Splits a comma-separated config value such as `feat,fix`
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            Header::parse("feat(parser)!: drop the legacy format"),
+            Some(Header {
+                kind: "feat".to_string(),
+                scope: Some("parser".to_string()),
+                breaking: true,
+                description: "drop the legacy format".to_string(),
+            })
+        );
+        assert_eq!(
+            Header::parse("fix: handle empty input").map(|header| header.scope),
+            Some(None)
+        );
+        assert_eq!(Header::parse("Fix the parser"), None);
+        assert_eq!(Header::parse("feat(two words): nope"), None);
+    }
+
+    #[test]
+    fn test_header_display() {
+        let header = Header::parse("feat(parser)!: drop the legacy format").unwrap();
+
+        assert_eq!(header.to_string(), "feat(parser)!: drop the legacy format");
+    }
+
+    #[test]
+    fn test_lint_valid_message() {
+        let message = "fix(cli): reject unknown flags\n\n\
+            Unknown flags used to be ignored.\n\
+            See https://example.com/a/very/long/link/that/goes/on/and/on/and/on/well/past/the/body/length/limit\n\
+            # Please enter the commit message for your changes";
+
+        assert_eq!(lint(message, &Rules::default()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_lint_problems() {
+        let rules = Rules {
+            types: split_list("feat,fix"),
+            subject_max_length: 20,
+            body_max_length: 10,
+            ..Rules::default()
+        };
+
+        assert_eq!(
+            lint("docs: update the readme.\nmore details here", &rules),
+            vec![
+                "Type 'docs' is not one of feat, fix".to_string(),
+                "The subject should not end with a period".to_string(),
+                "The subject is 24 characters long, the limit is 20".to_string(),
+                "Separate the subject from the body with a blank line".to_string(),
+            ]
+        );
+        assert_eq!(
+            lint("feat: add\n\nthis line is too long", &rules),
+            vec!["Line 3 is 21 characters long, the limit is 10".to_string()]
+        );
+        assert_eq!(
+            lint("# only a comment\n", &rules),
+            vec!["The commit message is empty".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_lint_ignores_verbose_diff() {
+        let rules = Rules {
+            comment: ";".to_string(),
+            ..Rules::default()
+        };
+        let message = format!(
+            "fix: handle empty input\n\
+             ; Please enter the commit message for your changes\n\
+             ; ------------------------ >8 ------------------------\n\
+             diff --git a/src/main.rs b/src/main.rs\n\
+             +{}\n",
+            "x".repeat(120)
+        );
+
+        assert_eq!(lint(&message, &rules), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_lint_imperative_mood() {
+        let rules = Rules::default();
+
+        assert_eq!(
+            lint("fix: added a check", &rules),
+            vec!["Start the subject with a verb in the imperative mood, e.g. 'fix' rather than 'added'".to_string()]
+        );
+        assert_eq!(lint("fix(cli): adds a check", &rules).len(), 1);
+        assert_eq!(lint("feat: adding a check", &rules).len(), 1);
+
+        for subject in [
+            "fix: add a check",
+            "fix: process queued jobs",
+            "feat: embed the font",
+            "fix: focus the input",
+        ] {
+            assert_eq!(lint(subject, &rules), Vec::<String>::new(), "{subject}");
+        }
+    }
+
+    #[test]
+    fn test_lint_without_conventional_commits() {
+        let rules = Rules {
+            conventional: false,
+            ..Rules::default()
+        };
+
+        assert_eq!(lint("Add a check", &rules), Vec::<String>::new());
+        assert_eq!(
+            lint("Fixed the parser", &rules),
+            vec!["Start the subject with a verb in the imperative mood, e.g. 'fix' rather than 'Fixed'".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_lint_required_trailers() {
+        let rules = Rules {
+            trailers: split_list("Signed-off-by,Refs"),
+            ..Rules::default()
+        };
+
+        assert_eq!(
+            lint(
+                "fix: add a check\n\nBody.\n\nsigned-off-by: Jane <jane@example.com>\n",
+                &rules
+            ),
+            vec!["The 'Refs' trailer is missing".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_lint_skips_fixups_and_merges() {
+        let rules = Rules::default();
+
+        assert_eq!(lint("fixup! Whatever it was", &rules), Vec::<String>::new());
+        assert_eq!(
+            lint("Merge branch 'main' into feature", &rules),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_trailers() {
+        assert_eq!(
+            trailers("feat: add login\n\nRefs: #12\nBREAKING CHANGE: sessions expire\n"),
+            vec![
+                ("Refs".to_string(), "#12".to_string()),
+                ("BREAKING CHANGE".to_string(), "sessions expire".to_string()),
+            ]
+        );
+        assert_eq!(trailers("Refs: #12"), Vec::new());
+    }
+
+    #[test]
+    fn test_rules_load() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.commitTypes"])
+            .times(1)
+            .returning(|_, _, _| Ok("feat, fix ,chore\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.lintImperative"])
+            .times(1)
+            .returning(|_, _, _| Ok("false\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.subjectMaxLength"])
+            .times(1)
+            .returning(|_, _, _| Ok("50\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.requiredTrailers"])
+            .times(1)
+            .returning(|_, _, _| Ok("Signed-off-by\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "core.commentChar"])
+            .times(1)
+            .returning(|_, _, _| Ok(";\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "config")
+            .times(2)
+            .returning(|_, _, _| Err(()));
+
+        let rules = Rules::load(&command, false);
+
+        assert_eq!(rules.types, ["feat", "fix", "chore"]);
+        assert!(rules.conventional);
+        assert!(!rules.imperative);
+        assert_eq!(rules.subject_max_length, 50);
+        assert_eq!(rules.body_max_length, DEFAULT_BODY_MAX_LENGTH);
+        assert_eq!(rules.trailers, ["Signed-off-by"]);
+        assert_eq!(rules.comment, ";");
+    }
+}
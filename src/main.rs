@@ -1,13 +1,22 @@
+use std::io;
+use std::process;
+
 use clap::Parser;
 
-use crate::cli::{Args, Commands};
+use crate::cli::{Args, Commands, Strategy};
 use crate::commands::{
-    autosquash, branch, checkout, cherry_pick, delete_branches, git_fallback, rebase, Cmd,
+    autosquash, branch, branches, changelog, check_commits, checkout, cherry_pick, commit,
+    delete_branches, git_fallback, hooks, land, lint, publish, push, rebase, release, stash, sync,
+    trash, worktree, Cmd,
 };
-use crate::utils::get_base;
+use crate::utils::{get_base, get_branch_base};
 
 mod cli;
 mod commands;
+mod config;
+mod conventional;
+mod parser;
+mod preflight;
 mod utils;
 
 fn main() {
@@ -15,32 +24,187 @@ fn main() {
 
     let command = Cmd {};
 
+    if cli.command.as_ref().is_some_and(Commands::mutates) {
+        if let Err(err) = preflight::check(&command, cli.verbose) {
+            println!("{err}");
+            process::exit(1);
+        }
+    }
+
     let result = match cli.command {
-        Some(Commands::Autosquash { number, base }) => {
+        Some(Commands::Autosquash { number, base, exec }) => {
             let base = get_base(&command, base, cli.verbose);
 
-            autosquash::run(&command, &base, number, cli.verbose)
+            autosquash::run(&command, &base, number, exec.as_deref(), cli.verbose)
         }
-        Some(Commands::Branch { name, base }) => {
+        Some(Commands::Branch {
+            name,
+            base,
+            publish,
+            no_publish,
+        }) => {
             let base = get_base(&command, base, cli.verbose);
 
-            branch::run(&command, &name, &base, cli.verbose)
+            // `lgit.publish` makes publishing the default, `--no-publish` opts out again
+            let publish = !no_publish
+                && (publish
+                    || config::get_bool(&command, "lgit.publish", cli.verbose).unwrap_or(false));
+
+            branch::run(&command, name, &base, publish, cli.verbose)
         }
-        Some(Commands::Checkout { name, remote, all }) => {
-            checkout::run(&command, name, remote, all, cli.verbose)
+        Some(Commands::Branches {
+            pattern,
+            base,
+            sort,
+            merged,
+            no_merged,
+            format,
+        }) => {
+            // An explicit base overrides the ones recorded per branch
+            let recorded_bases = base.is_none();
+
+            let options = branches::Options {
+                base: get_base(&command, base, cli.verbose),
+                recorded_bases,
+                pattern,
+                merged,
+                no_merged,
+                sort,
+                format,
+            };
+
+            branches::run(&command, &options, cli.verbose)
         }
-        Some(Commands::DeleteBranches { dry_run }) => {
+        Some(Commands::Checkout {
+            name,
+            remote,
+            all,
+            recent,
+            carry,
+            autostash,
+            pr,
+            worktree,
+            no_worktree,
+        }) => {
+            // `lgit.worktree` makes worktrees the default, `--no-worktree` opts out again
+            let worktree = !no_worktree
+                && (worktree
+                    || config::get_bool(&command, "lgit.worktree", cli.verbose).unwrap_or(false));
+
+            let options = checkout::Options {
+                remote,
+                all,
+                recent,
+                carry,
+                autostash,
+                pr,
+                worktree,
+            };
+
+            checkout::run(&command, name, &options, cli.verbose)
+        }
+        Some(Commands::DeleteBranches {
+            dry_run,
+            stale: Some(age),
+            author,
+            base,
+        }) => {
+            let base = get_base(&command, base, cli.verbose);
+
+            delete_branches::run_stale(
+                &command,
+                &age,
+                author.as_deref(),
+                &base,
+                dry_run,
+                cli.verbose,
+            )
+        }
+        Some(Commands::DeleteBranches { dry_run, .. }) => {
             delete_branches::run(&command, dry_run, cli.verbose)
         }
         Some(Commands::Fixup { number }) => commands::fixup::run(&command, number, cli.verbose),
-        Some(Commands::Rebase { base }) => {
+        Some(Commands::Land { base, squash }) => {
+            let base = get_branch_base(&command, base, cli.verbose);
+
+            land::run(&command, &base, squash, cli.verbose)
+        }
+        Some(Commands::Publish { base }) => {
             let base = get_base(&command, base, cli.verbose);
 
-            rebase::run(&command, &base, cli.verbose)
+            publish::run(&command, &base, cli.verbose)
+        }
+        Some(Commands::Push) => push::run(&command, cli.verbose),
+        Some(Commands::Rebase {
+            base,
+            strategy,
+            exec,
+            all,
+            resume,
+            abort,
+        }) => {
+            if resume || abort {
+                rebase::resume(&command, abort, cli.verbose)
+            } else if all {
+                let base = get_base(&command, base, cli.verbose);
+
+                rebase::run_all(&command, &base, cli.verbose)
+            } else {
+                let base = get_base(&command, base, cli.verbose);
+                let strategy =
+                    strategy.unwrap_or_else(|| rebase::get_strategy(&command, cli.verbose));
+
+                rebase::run(&command, &base, &strategy, exec.as_deref(), cli.verbose)
+            }
+        }
+        Some(Commands::Update { base }) => {
+            let base = get_base(&command, base, cli.verbose);
+
+            rebase::run(&command, &base, &Strategy::Merge, None, cli.verbose)
+        }
+        Some(Commands::Sync { rebase }) => sync::run(&command, rebase, cli.verbose),
+        Some(Commands::CheckCommits {
+            base,
+            exec,
+            fail_fast,
+        }) => {
+            let options = check_commits::Options {
+                base: get_branch_base(&command, base, cli.verbose),
+                exec,
+                fail_fast,
+            };
+
+            check_commits::run(&command, &options, cli.verbose)
         }
         Some(Commands::CherryPick { branch, number }) => {
             cherry_pick::run(&command, &branch, number, cli.verbose)
         }
+        Some(Commands::Stash { action }) => stash::run(&command, action, cli.verbose),
+        Some(Commands::Commit { message }) => commit::run(&command, message, cli.verbose),
+        Some(Commands::LintMsg { file }) => lint::run_msg(&command, &file, cli.verbose),
+        Some(Commands::LintBranch {
+            base,
+            pre_push: true,
+        }) => match io::read_to_string(io::stdin()) {
+            Ok(refs) => lint::run_pre_push(&command, &refs, base, cli.verbose),
+            Err(err) => Err(Some(format!("Failed to read the pushed refs: {}", err))),
+        },
+        Some(Commands::LintBranch { base, .. }) => {
+            let base = get_branch_base(&command, base, cli.verbose);
+
+            lint::run_branch(&command, &base, cli.verbose)
+        }
+        Some(Commands::Changelog { range, format }) => {
+            changelog::run(&command, range, &format, cli.verbose)
+        }
+        Some(Commands::Release { bump, pre, dry_run }) => {
+            let options = release::Options { bump, pre, dry_run };
+
+            release::run(&command, &options, cli.verbose)
+        }
+        Some(Commands::Hooks { action }) => hooks::run(&command, action, cli.verbose),
+        Some(Commands::Trash { action }) => trash::run(&command, action, cli.verbose),
+        Some(Commands::Worktree { action }) => worktree::run(&command, action, cli.verbose),
         Some(Commands::External(args)) => {
             // Handle 'co' alias for checkout
             // if !args.is_empty() && args[0] == "co" {
@@ -58,7 +222,12 @@ fn main() {
         )),
     };
 
-    if let Err(Some(err)) = result {
-        println!("{err}");
+    // A failing exit status lets scripts and hooks (e.g. `lgit lint-msg`) stop on errors
+    if let Err(err) = result {
+        if let Some(err) = err {
+            println!("{err}");
+        }
+
+        process::exit(1);
     }
 }
@@ -0,0 +1,463 @@
+use std::fmt;
+
+use crate::commands::Exec;
+
+/// `for-each-ref` format matching the fields of [`Branch`], NUL-delimited so that no field value
+/// (e.g. a commit subject) can be mistaken for a separator
+const BRANCH_FORMAT: &str = "--format=%(HEAD)%00%(refname)%00%(upstream)%00%(upstream:track)%00%(committerdate:unix)%00%(authorname) %(authoremail)%00";
+const BRANCH_FIELDS: usize = 6;
+
+/// `reflog` format printing only the reflog subject, e.g. `checkout: moving from main to feature`
+const REFLOG_FORMAT: &str = "--format=%gs";
+
+/// `stash list -z` format matching the fields of [`Stash`]
+const STASH_FORMAT: &str = "--format=%gd%x00%H%x00%ct%x00%gs";
+const STASH_FIELDS: usize = 4;
+
+/// `log -z` format matching the fields of [`Message`]
+const MESSAGE_FORMAT: &str = "--format=%H%x00%B";
+const MESSAGE_FIELDS: usize = 2;
+
+/// `log -z` format matching the fields of [`Commit`]
+const COMMIT_FORMAT: &str = "--format=%h%x00%s";
+const COMMIT_FIELDS: usize = 2;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Worktree {
+    pub path: String,
+    pub head: String,
+    /// Checked out branch without the `refs/heads/` prefix, `None` when detached or bare
+    pub branch: Option<String>,
+    pub bare: bool,
+    /// Whether the worktree directory is gone and `git worktree prune` would remove it
+    pub prunable: bool,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Track {
+    pub ahead: u32,
+    pub behind: u32,
+    pub gone: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Branch {
+    pub refname: String,
+    pub head: bool,
+    pub upstream: Option<String>,
+    pub track: Track,
+    /// Unix timestamp, `None` when git gave none that parses
+    pub committer_date: Option<u64>,
+    pub author: String,
+}
+
+impl Branch {
+    /// Branch name without the `refs/heads/` or `refs/remotes/` prefix
+    pub fn name(&self) -> &str {
+        self.refname
+            .strip_prefix("refs/heads/")
+            .or_else(|| self.refname.strip_prefix("refs/remotes/"))
+            .unwrap_or(&self.refname)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Commit {
+    pub sha: String,
+    pub subject: String,
+}
+
+/// A commit with its full message
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub sha: String,
+    /// Subject, body and trailers as written, ending with a newline
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stash {
+    /// Reflog selector such as `stash@{0}`
+    pub reference: String,
+    pub sha: String,
+    /// Unix timestamp of when the changes were stashed
+    pub timestamp: u64,
+    /// Stash subject such as `On main: message` or `WIP on main: abc1234 subject`
+    pub subject: String,
+}
+
+impl Stash {
+    /// Branch the changes were stashed on, `(no branch)` when HEAD was detached
+    pub fn branch(&self) -> &str {
+        self.split().0
+    }
+
+    /// Stash message without the branch, e.g. `message` or `abc1234 subject`
+    pub fn message(&self) -> &str {
+        self.split().1
+    }
+
+    fn split(&self) -> (&str, &str) {
+        let (prefix, message) = self
+            .subject
+            .split_once(": ")
+            .unwrap_or(("", self.subject.as_str()));
+        let branch = prefix
+            .strip_prefix("WIP on ")
+            .or_else(|| prefix.strip_prefix("On "))
+            .unwrap_or(prefix);
+
+        (branch, message)
+    }
+}
+
+impl fmt::Display for Commit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.sha, self.subject)
+    }
+}
+
+/// Lists branches matching the given `for-each-ref` patterns (e.g. `refs/heads`)
+pub fn branches<T: Exec + ?Sized>(
+    command: &T,
+    patterns: &[&str],
+    verbose: bool,
+) -> Result<Vec<Branch>, ()> {
+    let mut args = vec!["for-each-ref", BRANCH_FORMAT];
+    args.extend(patterns);
+
+    let output = command.exec(&args, verbose, false)?;
+
+    Ok(parse_branches(&output))
+}
+
+/// Lists commits returned by `git log` for the given arguments (e.g. `["-n", "25", "main"]`)
+pub fn commits<T: Exec + ?Sized>(
+    command: &T,
+    args: &[&str],
+    verbose: bool,
+) -> Result<Vec<Commit>, ()> {
+    let mut log_args = vec!["log", "-z", COMMIT_FORMAT];
+    log_args.extend(args);
+
+    let output = command.exec(&log_args, verbose, false)?;
+
+    Ok(parse_commits(&output))
+}
+
+/// Lists all stash entries, most recent first
+pub fn stashes<T: Exec + ?Sized>(command: &T, verbose: bool) -> Result<Vec<Stash>, ()> {
+    let output = command.exec(&["stash", "list", "-z", STASH_FORMAT], verbose, false)?;
+
+    Ok(parse_stashes(&output))
+}
+
+/// Lists commits returned by `git log` for the given arguments along with their full messages
+pub fn messages<T: Exec + ?Sized>(
+    command: &T,
+    args: &[&str],
+    verbose: bool,
+) -> Result<Vec<Message>, ()> {
+    let mut log_args = vec!["log", "-z", MESSAGE_FORMAT];
+    log_args.extend(args);
+
+    let output = command.exec(&log_args, verbose, false)?;
+
+    Ok(parse_messages(&output))
+}
+
+/// Lists the branches left by each checkout recorded in the HEAD reflog, most recent first, so
+/// that the entry at index `n - 1` is the branch `@{-n}` refers to
+pub fn checkouts<T: Exec + ?Sized>(command: &T, verbose: bool) -> Result<Vec<String>, ()> {
+    let output = command.exec(
+        &["reflog", "show", "-z", REFLOG_FORMAT, "HEAD"],
+        verbose,
+        false,
+    )?;
+
+    Ok(parse_checkouts(&output))
+}
+
+/// Lists all worktrees of the repository, the main worktree first
+pub fn worktrees<T: Exec + ?Sized>(command: &T, verbose: bool) -> Result<Vec<Worktree>, ()> {
+    let output = command.exec(&["worktree", "list", "--porcelain", "-z"], verbose, false)?;
+
+    Ok(parse_worktrees(&output))
+}
+
+fn parse_branches(output: &str) -> Vec<Branch> {
+    records(output, BRANCH_FIELDS)
+        .into_iter()
+        .map(|fields| Branch {
+            head: fields[0] == "*",
+            refname: fields[1].to_string(),
+            upstream: Some(fields[2].to_string()).filter(|upstream| !upstream.is_empty()),
+            track: parse_track(fields[3]),
+            committer_date: fields[4].parse().ok(),
+            author: fields[5].to_string(),
+        })
+        .collect()
+}
+
+fn parse_commits(output: &str) -> Vec<Commit> {
+    records(output, COMMIT_FIELDS)
+        .into_iter()
+        .map(|fields| Commit {
+            sha: fields[0].to_string(),
+            subject: fields[1].to_string(),
+        })
+        .collect()
+}
+
+fn parse_messages(output: &str) -> Vec<Message> {
+    records(output, MESSAGE_FIELDS)
+        .into_iter()
+        .map(|fields| Message {
+            sha: fields[0].to_string(),
+            message: fields[1].to_string(),
+        })
+        .collect()
+}
+
+fn parse_stashes(output: &str) -> Vec<Stash> {
+    records(output, STASH_FIELDS)
+        .into_iter()
+        .map(|fields| Stash {
+            reference: fields[0].to_string(),
+            sha: fields[1].to_string(),
+            timestamp: fields[2].parse().unwrap_or(0),
+            subject: fields[3].to_string(),
+        })
+        .collect()
+}
+
+fn parse_checkouts(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter_map(|subject| subject.trim().strip_prefix("checkout: moving from "))
+        .filter_map(|moving| moving.split_once(" to "))
+        .map(|(from, _)| from.to_string())
+        .collect()
+}
+
+/// Parses `worktree list --porcelain -z` output, where each attribute is NUL-terminated and each
+/// worktree is terminated by an additional NUL
+fn parse_worktrees(output: &str) -> Vec<Worktree> {
+    output
+        .split("\0\0")
+        .filter_map(|record| {
+            let mut worktree = Worktree::default();
+
+            for attribute in record.split('\0') {
+                let (key, value) = attribute.split_once(' ').unwrap_or((attribute, ""));
+
+                match key {
+                    "worktree" => worktree.path = value.to_string(),
+                    "HEAD" => worktree.head = value.to_string(),
+                    "branch" => {
+                        worktree.branch = Some(
+                            value
+                                .strip_prefix("refs/heads/")
+                                .unwrap_or(value)
+                                .to_string(),
+                        )
+                    }
+                    "bare" => worktree.bare = true,
+                    "prunable" => worktree.prunable = true,
+                    _ => {}
+                }
+            }
+
+            Some(worktree).filter(|worktree| !worktree.path.is_empty())
+        })
+        .collect()
+}
+
+/// Parses `%(upstream:track)` values such as `[ahead 1, behind 2]` or `[gone]`
+fn parse_track(track: &str) -> Track {
+    let mut result = Track::default();
+
+    for part in track.trim_matches(|c| c == '[' || c == ']').split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", count)) => result.ahead = count.parse().unwrap_or(0),
+            Some(("behind", count)) => result.behind = count.parse().unwrap_or(0),
+            _ if part == "gone" => result.gone = true,
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Splits NUL-delimited output into records of `fields` fields each. Records may additionally be
+/// separated by the newline `for-each-ref` prints after each of them.
+fn records(output: &str, fields: usize) -> Vec<Vec<&str>> {
+    let values: Vec<&str> = output.split('\0').collect();
+
+    values
+        .chunks(fields)
+        .filter(|record| record.len() == fields)
+        .map(|record| {
+            let mut record = record.to_vec();
+            record[0] = record[0].trim_start_matches('\n');
+            record
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    #[test]
+    fn test_parse_branches() {
+        let output = "*\0refs/heads/main\0refs/remotes/origin/main\0[behind 2]\x001700000000\0Jane <jane@example.com>\0\n \
+            \0refs/heads/old\0refs/remotes/origin/old\0[gone]\x001600000000\0John <john@example.com>\0\n \
+            \0refs/remotes/origin/HEAD\0\0\0\0\0\n";
+
+        let branches = parse_branches(output);
+
+        assert_eq!(branches.len(), 3);
+        assert!(branches[0].head);
+        assert_eq!(branches[0].name(), "main");
+        assert_eq!(
+            branches[0].upstream,
+            Some("refs/remotes/origin/main".to_string())
+        );
+        assert_eq!(branches[0].track.behind, 2);
+        assert_eq!(branches[0].committer_date, Some(1_700_000_000));
+        assert!(!branches[1].head);
+        assert!(branches[1].track.gone);
+        assert_eq!(branches[1].author, "John <john@example.com>");
+        assert_eq!(branches[2].name(), "origin/HEAD");
+        assert_eq!(branches[2].upstream, None);
+    }
+
+    #[test]
+    fn test_parse_branches_missing_date() {
+        let branches = parse_branches(" \0refs/heads/main\0\0\0\0\0\n");
+
+        assert_eq!(branches[0].committer_date, None);
+    }
+
+    #[test]
+    fn test_parse_stashes() {
+        let stashes = parse_stashes("stash@{0}\0abc123\x001760882700\0On main: lgit-autostash:main\0stash@{1}\0def456\x001760882700\0WIP on feature: 1234567 Work\0");
+
+        assert_eq!(stashes.len(), 2);
+        assert_eq!(stashes[0].reference, "stash@{0}");
+        assert_eq!(stashes[0].subject, "On main: lgit-autostash:main");
+        assert_eq!(stashes[1].sha, "def456");
+        assert_eq!(stashes[1].timestamp, 1_760_882_700);
+    }
+
+    #[test]
+    fn test_parse_messages() {
+        let messages =
+            parse_messages("abc123\0feat: add login\n\nWith a form.\n\0\ndef456\0fix: typo\n\0");
+
+        assert_eq!(
+            messages,
+            vec![
+                Message {
+                    sha: "abc123".to_string(),
+                    message: "feat: add login\n\nWith a form.\n".to_string(),
+                },
+                Message {
+                    sha: "def456".to_string(),
+                    message: "fix: typo\n".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stash_branch_and_message() {
+        let stashes = parse_stashes("stash@{0}\0abc123\x001760882700\0On main: lgit-autostash:main\0stash@{1}\0def456\x001760882700\0WIP on feature: 1234567 Work: done\0");
+
+        assert_eq!(stashes[0].branch(), "main");
+        assert_eq!(stashes[0].message(), "lgit-autostash:main");
+        assert_eq!(stashes[1].branch(), "feature");
+        assert_eq!(stashes[1].message(), "1234567 Work: done");
+    }
+
+    #[test]
+    fn test_parse_checkouts() {
+        let output = "checkout: moving from feature to main\0commit: Add feature\0\
+            checkout: moving from main to feature\0pull: Fast-forward\0";
+
+        assert_eq!(parse_checkouts(output), vec!["feature", "main"]);
+    }
+
+    #[test]
+    fn test_parse_worktrees() {
+        let output = "worktree /repo\0HEAD abc123\0branch refs/heads/main\0\0\
+            worktree /repo.worktrees/feature/x\0HEAD def456\0branch refs/heads/feature/x\0\0\
+            worktree /tmp/gone\0HEAD fed789\0detached\0prunable gitdir file points to non-existent location\0\0";
+
+        let worktrees = parse_worktrees(output);
+
+        assert_eq!(worktrees.len(), 3);
+        assert_eq!(worktrees[0].path, "/repo");
+        assert_eq!(worktrees[0].branch, Some("main".to_string()));
+        assert_eq!(worktrees[1].branch, Some("feature/x".to_string()));
+        assert_eq!(worktrees[2].head, "fed789");
+        assert_eq!(worktrees[2].branch, None);
+        assert!(worktrees[2].prunable);
+        assert!(!worktrees[0].prunable);
+    }
+
+    #[test]
+    fn test_parse_track() {
+        assert_eq!(
+            parse_track("[ahead 1, behind 2]"),
+            Track {
+                ahead: 1,
+                behind: 2,
+                gone: false
+            }
+        );
+        assert!(parse_track("[gone]").gone);
+        assert_eq!(parse_track(""), Track::default());
+    }
+
+    #[test]
+    fn test_parse_commits_with_separators_in_subject() {
+        let commits = parse_commits("abc123\0fix: handle \": gone]\" text\0def456\0Second commit");
+
+        assert_eq!(
+            commits,
+            vec![
+                Commit {
+                    sha: "abc123".to_string(),
+                    subject: "fix: handle \": gone]\" text".to_string()
+                },
+                Commit {
+                    sha: "def456".to_string(),
+                    subject: "Second commit".to_string()
+                },
+            ]
+        );
+        assert_eq!(commits[1].to_string(), "def456 Second commit");
+    }
+
+    #[test]
+    fn test_parse_commits_empty() {
+        assert!(parse_commits("").is_empty());
+    }
+
+    #[test]
+    fn test_branches_failure() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["for-each-ref", BRANCH_FORMAT, "refs/heads"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let result = branches(&command, &["refs/heads"], false);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use crate::commands::Exec;
+
+/// A git operation that stopped halfway, waiting for the user to finish or abort it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Rebase,
+    Merge,
+    CherryPick,
+    Bisect,
+    /// Another git process holds the index, or crashed while holding it
+    IndexLock,
+}
+
+/// Files git keeps in the git directory while an operation is in progress
+const STATE_FILES: [(&str, Operation); 6] = [
+    ("rebase-merge", Operation::Rebase),
+    ("rebase-apply", Operation::Rebase),
+    ("MERGE_HEAD", Operation::Merge),
+    ("CHERRY_PICK_HEAD", Operation::CherryPick),
+    ("BISECT_LOG", Operation::Bisect),
+    ("index.lock", Operation::IndexLock),
+];
+
+/// Refuses to go on while another git operation is in progress, which commands changing branches,
+/// commits or the working tree would otherwise interfere with
+pub fn check<T: Exec + ?Sized>(command: &T, verbose: bool) -> Result<(), String> {
+    match find(command, verbose) {
+        Some((operation, path)) => Err(hint(operation, &path)),
+        None => Ok(()),
+    }
+}
+
+/// The operation in progress in the current worktree, if any
+pub fn in_progress<T: Exec + ?Sized>(command: &T, verbose: bool) -> Option<Operation> {
+    find(command, verbose).map(|(operation, _)| operation)
+}
+
+fn find<T: Exec + ?Sized>(command: &T, verbose: bool) -> Option<(Operation, String)> {
+    let mut args = vec!["rev-parse"];
+
+    for (file, _) in &STATE_FILES {
+        args.extend(["--git-path", file]);
+    }
+
+    // Resolved by git, as they live in the worktree's own git directory
+    let paths = command.exec(&args, verbose, false).ok()?;
+
+    paths
+        .lines()
+        .zip(STATE_FILES)
+        .find(|(path, _)| Path::new(path).exists())
+        .map(|(path, (_, operation))| (operation, path.to_string()))
+}
+
+fn hint(operation: Operation, path: &str) -> String {
+    match operation {
+        Operation::Rebase => "A rebase is in progress, finish it with 'lgit rebase --continue' or abort it with 'lgit rebase --abort'".to_string(),
+        Operation::Merge => "A merge is in progress, finish it with 'lgit rebase --continue' or abort it with 'lgit rebase --abort'".to_string(),
+        Operation::CherryPick => "A cherry-pick is in progress, finish it with 'git cherry-pick --continue' or abort it with 'git cherry-pick --abort'".to_string(),
+        Operation::Bisect => "A bisect is in progress, end it with 'git bisect reset'".to_string(),
+        Operation::IndexLock => format!(
+            "Another git process seems to be running ('{}' exists), wait for it to finish or remove the file if it crashed",
+            path
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::MockCmd;
+
+    /// Mocks the git directory with only `state_file` present
+    fn cmd_state_file(state_file: Option<&'static str>) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, verbose, inherit_stderr| {
+                args.len() == 13
+                    && args[0] == "rev-parse"
+                    && args[2] == "rebase-merge"
+                    && args[12] == "index.lock"
+                    && !(*verbose)
+                    && !(*inherit_stderr)
+            })
+            .times(1)
+            .returning(move |_, _, _| {
+                let paths: Vec<String> = STATE_FILES
+                    .iter()
+                    .map(|(file, _)| match state_file {
+                        // An existing file stands in for the state file
+                        Some(state_file) if state_file == *file => {
+                            format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"))
+                        }
+                        _ => format!("/nonexistent/.git/{file}"),
+                    })
+                    .collect();
+
+                Ok(paths.join("\n") + "\n")
+            });
+
+        command
+    }
+
+    #[test]
+    fn test_check_clean() {
+        assert_eq!(check(&cmd_state_file(None), false), Ok(()));
+    }
+
+    #[test]
+    fn test_check_cherry_pick() {
+        assert_eq!(
+            check(&cmd_state_file(Some("CHERRY_PICK_HEAD")), false),
+            Err("A cherry-pick is in progress, finish it with 'git cherry-pick --continue' or abort it with 'git cherry-pick --abort'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_index_lock_names_file() {
+        let result = check(&cmd_state_file(Some("index.lock")), false);
+
+        assert!(result.unwrap_err().contains("Cargo.toml"));
+    }
+
+    #[test]
+    fn test_in_progress() {
+        assert_eq!(
+            in_progress(&cmd_state_file(Some("rebase-apply")), false),
+            Some(Operation::Rebase)
+        );
+        assert_eq!(
+            in_progress(&cmd_state_file(Some("BISECT_LOG")), false),
+            Some(Operation::Bisect)
+        );
+    }
+
+    #[test]
+    fn test_in_progress_outside_repository() {
+        let mut command = MockCmd::new();
+        command.expect_exec().returning(|_, _, _| Err(()));
+
+        assert_eq!(in_progress(&command, false), None);
+    }
+}
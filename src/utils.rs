@@ -1,4 +1,15 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use crate::commands::Exec;
+use crate::config;
+use crate::parser;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Prefix of the message of stashes lgit takes while running an operation, followed by the
+/// operation and the time, e.g. `lgit-stash:rebase 2026-10-19 14:05 UTC`
+pub const STASH_PREFIX: &str = "lgit-stash:";
 
 pub fn get_default_branch<T: Exec>(command: &T, verbose: bool) -> Result<&'static str, String> {
     for branch in ["main", "master"] {
@@ -17,9 +28,45 @@ pub fn get_base<T: Exec>(command: &T, base: Option<String>, verbose: bool) -> St
     })
 }
 
-pub fn refresh_base<'a, T: Exec>(command: &T, base: &'a str, verbose: bool) -> Result<&'a str, ()> {
-    command.exec(&["checkout", base], verbose, false)?;
-    command.exec(&["pull"], verbose, false).map(|_| base)
+/// Base of the current branch: `base` when given, otherwise the one recorded by `lgit branch`,
+/// falling back to the default branch
+pub fn get_branch_base<T: Exec>(command: &T, base: Option<String>, verbose: bool) -> String {
+    let base = base.or_else(|| {
+        get_current_branch(command, verbose)
+            .and_then(|branch| get_recorded_base(command, &branch, verbose))
+    });
+
+    get_base(command, base, verbose)
+}
+
+/// Fetches `base` from the configured remote without touching the working tree and returns the
+/// ref to branch or rebase from: `<remote>/<base>`, or `base` itself if the remote doesn't track it.
+/// The local base is fast-forwarded along unless `lgit.fastForwardBase` is false.
+pub fn refresh_base<T: Exec>(command: &T, base: &str, verbose: bool) -> Result<String, ()> {
+    let remote = get_remote(command, verbose);
+
+    // Fetching into `base` refuses to update it when it's checked out or has diverged, in which
+    // case only the remote-tracking branch is updated
+    let fast_forwarded = config::get_bool(command, "lgit.fastForwardBase", verbose).unwrap_or(true)
+        && command
+            .exec(
+                &["fetch", &remote, &format!("{base}:{base}")],
+                verbose,
+                false,
+            )
+            .is_ok();
+
+    if !fast_forwarded {
+        command.exec(&["fetch", &remote, base], verbose, false)?;
+    }
+
+    let remote_base = format!("{remote}/{base}");
+
+    if ref_exists(command, &format!("refs/remotes/{remote_base}"), verbose) {
+        Ok(remote_base)
+    } else {
+        Ok(base.to_string())
+    }
 }
 
 fn search_branch<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<(), Option<String>> {
@@ -34,30 +81,299 @@ fn search_branch<T: Exec>(command: &T, branch: &str, verbose: bool) -> Result<()
     }
 }
 
-pub fn stash<T: Exec>(command: &T, verbose: bool) -> Result<bool, String> {
+/// Remote lgit fetches from and pushes to, configured with `lgit.remote` (defaults to `origin`)
+pub fn get_remote<T: Exec + ?Sized>(command: &T, verbose: bool) -> String {
+    config::get(command, "lgit.remote", verbose).unwrap_or_else(|| "origin".to_string())
+}
+
+/// Whether the fully qualified `refname` (e.g. `refs/heads/main`) exists
+pub fn ref_exists<T: Exec + ?Sized>(command: &T, refname: &str, verbose: bool) -> bool {
+    command
+        .exec(
+            &["show-ref", "--verify", "--quiet", refname],
+            verbose,
+            false,
+        )
+        .is_ok()
+}
+
+/// Whether every commit of `ancestor` is part of `descendant`
+pub fn is_ancestor<T: Exec + ?Sized>(
+    command: &T,
+    ancestor: &str,
+    descendant: &str,
+    verbose: bool,
+) -> bool {
+    command
+        .exec(
+            &["merge-base", "--is-ancestor", ancestor, descendant],
+            verbose,
+            false,
+        )
+        .is_ok()
+}
+
+/// Whether `branch` matches one of the comma-separated patterns of `lgit.protected` (`main` and
+/// `master` by default), where a trailing `*` matches any suffix (e.g. `release/*`)
+pub fn is_protected<T: Exec + ?Sized>(command: &T, branch: &str, verbose: bool) -> bool {
+    matches_protected(branch, &protected_patterns(command, verbose))
+}
+
+/// The patterns of `lgit.protected`, for checking many branches with [`matches_protected`]
+pub fn protected_patterns<T: Exec + ?Sized>(command: &T, verbose: bool) -> Vec<String> {
+    config::get(command, "lgit.protected", verbose)
+        .unwrap_or_else(|| "main,master".to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub fn matches_protected(branch: &str, patterns: &[String]) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => branch.starts_with(prefix),
+            None => branch == pattern,
+        })
+}
+
+/// Records the base `branch` was created from, so that it can be rebased onto it later on
+pub fn set_recorded_base<T: Exec + ?Sized>(
+    command: &T,
+    branch: &str,
+    base: &str,
+    verbose: bool,
+) -> Result<(), ()> {
+    command
+        .exec(
+            &["config", &format!("branch.{branch}.lgitBase"), base],
+            verbose,
+            false,
+        )
+        .map(|_| ())
+}
+
+/// Base `branch` was created from by `lgit branch`, if any
+pub fn get_recorded_base<T: Exec + ?Sized>(
+    command: &T,
+    branch: &str,
+    verbose: bool,
+) -> Option<String> {
+    config::get(command, &format!("branch.{branch}.lgitBase"), verbose)
+}
+
+/// Bases recorded by `lgit branch` for all branches, keyed by branch name
+pub fn get_recorded_bases<T: Exec + ?Sized>(command: &T, verbose: bool) -> HashMap<String, String> {
+    // Fails when nothing matches
+    let output = command
+        .exec(
+            &["config", "--get-regexp", r"^branch\..*\.lgitbase$"],
+            verbose,
+            false,
+        )
+        .unwrap_or_default();
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, base) = line.split_once(' ')?;
+            let branch = key.strip_prefix("branch.")?.strip_suffix(".lgitbase")?;
+
+            Some((branch.to_string(), base.to_string()))
+        })
+        .collect()
+}
+
+/// Name of the checked out branch, `None` when HEAD is detached
+pub fn get_current_branch<T: Exec + ?Sized>(command: &T, verbose: bool) -> Option<String> {
+    command
+        .exec(&["symbolic-ref", "--short", "-q", "HEAD"], verbose, false)
+        .ok()
+        .map(|branch| branch.trim().to_string())
+        .filter(|branch| !branch.is_empty())
+}
+
+/// Stashes uncommitted changes for the duration of `operation`, tagged with the operation and the
+/// time so that stashes left behind can be told apart. Returns the SHA of the stash, or `None` when
+/// there is nothing to stash.
+pub fn stash<T: Exec>(
+    command: &T,
+    operation: &str,
+    verbose: bool,
+) -> Result<Option<String>, String> {
     let result = command
         .exec(&["status", "--porcelain"], verbose, false)
         .map_err(|()| "Failed to retrieve git status (check if in git repository)".to_string())?;
 
     if result.is_empty() {
-        return Ok(false);
+        return Ok(None);
     }
 
-    command
-        .exec(&["stash", "-u"], verbose, false)
-        .map_err(|()| "Failed to stash uncommitted changes".to_string())?;
+    let message = format!("{STASH_PREFIX}{operation} {}", format_time(now()));
 
-    Ok(true)
+    push_stash(command, &message, verbose)
+        .map_err(|()| "Failed to stash uncommitted changes".to_string())
 }
 
-pub fn unstash<T: Exec>(command: &T, verbose: bool) -> Result<(), Option<String>> {
+/// Stashes uncommitted changes including untracked files, returning the SHA of the new stash, or
+/// `None` when git found nothing it could stash (e.g. when only a submodule has changes)
+pub fn push_stash<T: Exec>(
+    command: &T,
+    message: &str,
+    verbose: bool,
+) -> Result<Option<String>, ()> {
+    let latest = || {
+        command
+            .exec(
+                &["rev-parse", "--verify", "--quiet", "refs/stash"],
+                verbose,
+                false,
+            )
+            .ok()
+            .map(|sha| sha.trim().to_string())
+    };
+
+    let previous = latest();
+
+    command.exec(
+        &["stash", "push", "--include-untracked", "--message", message],
+        verbose,
+        false,
+    )?;
+
+    // Saving nothing still succeeds, leaving the previous stash, possibly the user's own, on top
+    Ok(latest().filter(|sha| Some(sha) != previous.as_ref()))
+}
+
+/// Restores and drops the stash with the given SHA, wherever it ended up in the stash list. On
+/// conflicts git keeps the stash, so the error tells where to find it.
+pub fn unstash<T: Exec>(command: &T, sha: &str, verbose: bool) -> Result<(), Option<String>> {
+    let short = &sha[..sha.len().min(7)];
+
+    let stashes =
+        parser::stashes(command, verbose).map_err(|()| "Failed to list stashes".to_string())?;
+
+    let Some(stash) = stashes.iter().find(|stash| stash.sha == sha) else {
+        return Err(Some(format!(
+            "Failed to restore stashed changes, stash {} no longer exists",
+            short
+        )));
+    };
+
     command
-        .exec(&["stash", "pop"], verbose, false)
-        .map_err(|()| "Failed to restore stashed changes (use 'git stash pop' manually)".to_string())?;
+        .exec(&["stash", "pop", &stash.reference], verbose, false)
+        .map_err(|()| {
+            format!(
+                "Failed to restore stashed changes, they are kept in {} ({}). Resolve the conflicts and drop it with 'git stash drop {}', or find it later with 'lgit stash recover'",
+                stash.reference, short, stash.reference
+            )
+        })?;
 
     Ok(())
 }
 
+/// Counts the commits `branch` has that `other` doesn't (ahead) and the other way around (behind)
+pub fn ahead_behind<T: Exec + ?Sized>(
+    command: &T,
+    branch: &str,
+    other: &str,
+    verbose: bool,
+) -> Result<(u32, u32), ()> {
+    let counts = command.exec(
+        &[
+            "rev-list",
+            "--left-right",
+            "--count",
+            &format!("{branch}...{other}"),
+        ],
+        verbose,
+        false,
+    )?;
+
+    let mut counts = counts
+        .split_whitespace()
+        .map(|count| count.parse().unwrap_or(0));
+
+    Ok((counts.next().unwrap_or(0), counts.next().unwrap_or(0)))
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Formats a unix timestamp as a `YYYY-MM-DD` date (UTC).
+pub fn format_date(timestamp: u64) -> String {
+    // Civil-from-days conversion, see https://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM UTC`
+pub fn format_time(timestamp: u64) -> String {
+    let minutes = timestamp % SECONDS_PER_DAY / 60;
+
+    format!(
+        "{} {:02}:{:02} UTC",
+        format_date(timestamp),
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Formats a duration in seconds as a short age such as `45m`, `5h`, `3d`, `2w` or `1y`.
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=3_599 => format!("{}m", seconds / 60),
+        3_600..=86_399 => format!("{}h", seconds / 3_600),
+        86_400..=1_209_599 => format!("{}d", seconds / SECONDS_PER_DAY),
+        1_209_600..=31_535_999 => format!("{}w", seconds / (7 * SECONDS_PER_DAY)),
+        _ => format!("{}y", seconds / (365 * SECONDS_PER_DAY)),
+    }
+}
+
+/// Parses an age such as `30d`, `2w`, `6m` or `1y` into a number of days.
+pub fn parse_days(age: &str) -> Result<u64, String> {
+    let error = || format!("Invalid age '{}' (expected e.g. 30d, 2w, 6m or 1y)", age);
+
+    let age = age.trim();
+    let (number, multiplier) = match age.chars().last() {
+        Some('d') => (&age[..age.len() - 1], 1),
+        Some('w') => (&age[..age.len() - 1], 7),
+        Some('m') => (&age[..age.len() - 1], 30),
+        Some('y') => (&age[..age.len() - 1], 365),
+        Some(c) if c.is_ascii_digit() => (age, 1),
+        _ => return Err(error()),
+    };
+
+    number
+        .parse::<u64>()
+        .map(|number| number * multiplier)
+        .map_err(|_| error())
+}
+
+pub fn days_to_seconds(days: u64) -> u64 {
+    days * SECONDS_PER_DAY
+}
+
 #[cfg(test)]
 mod tests {
     use crate::commands::MockCmd;
@@ -101,15 +417,25 @@ mod tests {
         command
     }
 
-    fn cmd_checkout_main() -> MockCmd {
+    fn cmd_fetch_base(fast_forward: Option<bool>, fetched: bool) -> MockCmd {
         let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--type=bool", "--get", "lgit.fastForwardBase"])
+            .times(1)
+            .returning(move |_, _, _| fast_forward.map(|value| value.to_string()).ok_or(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.remote"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
+                args == ["fetch", "origin", "main"] && !(*verbose) && !(*inherit_stderr)
             })
-            .times(1)
-            .returning(|_, _, _| Ok(String::new()));
+            .times(0..=1)
+            .returning(move |_, _, _| if fetched { Ok(String::new()) } else { Err(()) });
 
         command
     }
@@ -155,53 +481,149 @@ mod tests {
     }
 
     #[test]
-    fn test_refresh_base_success() {
-        let mut command = cmd_checkout_main();
+    fn test_refresh_base_fast_forwards_local_base() {
+        let mut command = cmd_fetch_base(None, true);
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
+            .withf(|args, _, _| args == ["fetch", "origin", "main:main"])
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| {
+                args == [
+                    "show-ref",
+                    "--verify",
+                    "--quiet",
+                    "refs/remotes/origin/main",
+                ]
             })
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
         let result = super::refresh_base(&command, "main", false);
 
-        assert!(result.is_ok());
+        assert_eq!(result, Ok("origin/main".to_string()));
     }
 
     #[test]
-    fn test_refresh_base_checkout_failure() {
-        let mut command = MockCmd::new();
+    fn test_refresh_base_checked_out_or_diverged() {
+        let mut command = cmd_fetch_base(None, true);
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["checkout", "main"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args == ["fetch", "origin", "main:main"])
             .times(1)
             .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "show-ref")
+            .times(1)
+            .returning(|_, _, _| Ok(String::new()));
 
         let result = super::refresh_base(&command, "main", false);
 
-        assert!(result.is_err());
+        assert_eq!(result, Ok("origin/main".to_string()));
     }
 
     #[test]
-    fn test_refresh_base_pull_failure() {
-        let mut command = cmd_checkout_main();
+    fn test_refresh_base_without_fast_forward() {
+        let mut command = cmd_fetch_base(Some(false), true);
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["pull"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args[0] == "show-ref")
             .times(1)
             .returning(|_, _, _| Err(()));
 
         let result = super::refresh_base(&command, "main", false);
 
+        assert_eq!(result, Ok("main".to_string()));
+    }
+
+    #[test]
+    fn test_refresh_base_fetch_failure() {
+        let command = cmd_fetch_base(Some(false), false);
+
+        let result = super::refresh_base(&command, "main", false);
+
         assert!(result.is_err());
     }
 
+    fn cmd_protected(patterns: Option<&'static str>) -> MockCmd {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "lgit.protected"])
+            .returning(move |_, _, _| patterns.map(str::to_string).ok_or(()));
+
+        command
+    }
+
+    #[test]
+    fn test_is_protected_defaults() {
+        let command = cmd_protected(None);
+
+        assert!(super::is_protected(&command, "main", false));
+        assert!(super::is_protected(&command, "master", false));
+        assert!(!super::is_protected(&command, "main-fix", false));
+    }
+
+    #[test]
+    fn test_is_protected_patterns() {
+        let command = cmd_protected(Some("develop, release/*\n"));
+
+        assert!(super::is_protected(&command, "develop", false));
+        assert!(super::is_protected(&command, "release/1.2", false));
+        assert!(!super::is_protected(&command, "main", false));
+    }
+
+    #[test]
+    fn test_get_branch_base_uses_recorded_base() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["symbolic-ref", "--short", "-q", "HEAD"])
+            .times(1)
+            .returning(|_, _, _| Ok("feature\n".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get", "branch.feature.lgitBase"])
+            .times(1)
+            .returning(|_, _, _| Ok("develop\n".to_string()));
+
+        assert_eq!(super::get_branch_base(&command, None, false), "develop");
+        assert_eq!(
+            super::get_branch_base(&MockCmd::new(), Some("main".to_string()), false),
+            "main"
+        );
+    }
+
+    #[test]
+    fn test_get_recorded_bases() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["config", "--get-regexp", r"^branch\..*\.lgitbase$"])
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("branch.feature/x.lgitbase main\nbranch.fix.lgitbase develop\n".to_string())
+            });
+
+        let bases = super::get_recorded_bases(&command, false);
+
+        assert_eq!(bases.len(), 2);
+        assert_eq!(bases["feature/x"], "main");
+        assert_eq!(bases["fix"], "develop");
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(super::format_age(59), "0m");
+        assert_eq!(super::format_age(7_200), "2h");
+        assert_eq!(super::format_age(3 * 86_400), "3d");
+        assert_eq!(super::format_age(21 * 86_400), "3w");
+        assert_eq!(super::format_age(400 * 86_400), "1y");
+    }
+
     #[test]
     fn test_search_branch_found() {
         let command = cmd_branch_main_found();
@@ -229,9 +651,9 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Ok(String::new()));
 
-        let result = super::stash(&command, false);
+        let result = super::stash(&command, "rebase", false);
 
-        assert_eq!(result, Ok(false));
+        assert_eq!(result, Ok(None));
     }
 
     #[test]
@@ -248,14 +670,58 @@ mod tests {
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "-u"] && !(*verbose) && !(*inherit_stderr)
+                args.starts_with(&["stash", "push", "--include-untracked", "--message"])
+                    && args[4].starts_with("lgit-stash:rebase ")
+                    && args[4].ends_with(" UTC")
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok("Saved working directory".to_string()));
 
-        let result = super::stash(&command, false);
+        expect_stash_refs(&mut command, None, "abc1234def");
+
+        let result = super::stash(&command, "rebase", false);
 
-        assert_eq!(result, Ok(true));
+        assert_eq!(result, Ok(Some("abc1234def".to_string())));
+    }
+
+    #[test]
+    fn test_stash_nothing_saved() {
+        let mut command = MockCmd::new();
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["status", "--porcelain"])
+            .times(1)
+            .returning(|_, _, _| Ok(" M submodule".to_string()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["stash", "push"]))
+            .times(1)
+            .returning(|_, _, _| Ok("No local changes to save".to_string()));
+        // The user's own stash stays on top
+        expect_stash_refs(&mut command, Some("fed789"), "fed789");
+
+        let result = super::stash(&command, "rebase", false);
+
+        assert_eq!(result, Ok(None));
+    }
+
+    fn expect_stash_refs(command: &mut MockCmd, before: Option<&'static str>, after: &'static str) {
+        let mut calls = 0;
+
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["rev-parse", "--verify", "--quiet", "refs/stash"])
+            .times(2)
+            .returning(move |_, _, _| {
+                calls += 1;
+
+                match calls {
+                    1 => before.map(|sha| format!("{sha}\n")).ok_or(()),
+                    _ => Ok(format!("{after}\n")),
+                }
+            });
     }
 
     #[test]
@@ -269,10 +735,13 @@ mod tests {
             .times(1)
             .returning(|_, _, _| Err(()));
 
-        let result = super::stash(&command, false);
+        let result = super::stash(&command, "rebase", false);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Failed to retrieve git status (check if in git repository)");
+        assert_eq!(
+            result.unwrap_err(),
+            "Failed to retrieve git status (check if in git repository)"
+        );
     }
 
     #[test]
@@ -288,47 +757,127 @@ mod tests {
 
         command
             .expect_exec()
-            .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "-u"] && !(*verbose) && !(*inherit_stderr)
-            })
+            .withf(|args, _, _| args.starts_with(&["stash", "push"]))
+            .times(1)
+            .returning(|_, _, _| Err(()));
+        command
+            .expect_exec()
+            .withf(|args, _, _| args[0] == "rev-parse")
             .times(1)
             .returning(|_, _, _| Err(()));
 
-        let result = super::stash(&command, false);
+        let result = super::stash(&command, "rebase", false);
 
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "Failed to stash uncommitted changes");
     }
 
+    fn expect_stash_list(command: &mut MockCmd) {
+        command
+            .expect_exec()
+            .withf(|args, _, _| args.starts_with(&["stash", "list", "-z"]))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok("stash@{0}\0fed789\x001760882700\0On main: other\0\
+                    stash@{1}\0abc1234def\x001760882700\0On feature: lgit-stash:rebase 2026-10-19 14:05 UTC\0"
+                    .to_string())
+            });
+    }
+
     #[test]
-    fn test_unstash_success() {
+    fn test_unstash_pops_stash_by_sha() {
         let mut command = MockCmd::new();
+        expect_stash_list(&mut command);
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "pop"] && !(*verbose) && !(*inherit_stderr)
+                args == ["stash", "pop", "stash@{1}"] && !(*verbose) && !(*inherit_stderr)
             })
             .times(1)
             .returning(|_, _, _| Ok("Applied stash".to_string()));
 
-        let result = super::unstash(&command, false);
+        let result = super::unstash(&command, "abc1234def", false);
 
         assert!(result.is_ok());
     }
 
     #[test]
-    fn test_unstash_failure() {
+    fn test_unstash_conflict_keeps_stash() {
+        let mut command = MockCmd::new();
+        expect_stash_list(&mut command);
+        command
+            .expect_exec()
+            .withf(|args, _, _| args == ["stash", "pop", "stash@{1}"])
+            .times(1)
+            .returning(|_, _, _| Err(()));
+
+        let result = super::unstash(&command, "abc1234def", false);
+
+        assert_eq!(
+            result,
+            Err(Some("Failed to restore stashed changes, they are kept in stash@{1} (abc1234). Resolve the conflicts and drop it with 'git stash drop stash@{1}', or find it later with 'lgit stash recover'".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unstash_missing_stash() {
+        let mut command = MockCmd::new();
+        expect_stash_list(&mut command);
+
+        let result = super::unstash(&command, "0000000000", false);
+
+        assert_eq!(
+            result,
+            Err(Some(
+                "Failed to restore stashed changes, stash 0000000 no longer exists".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(super::format_time(1_760_882_700), "2025-10-19 14:05 UTC");
+    }
+
+    #[test]
+    fn test_ahead_behind() {
         let mut command = MockCmd::new();
         command
             .expect_exec()
             .withf(|args, verbose, inherit_stderr| {
-                args == ["stash", "pop"] && !(*verbose) && !(*inherit_stderr)
+                args == ["rev-list", "--left-right", "--count", "feature...main"]
+                    && !(*verbose)
+                    && !(*inherit_stderr)
             })
             .times(1)
-            .returning(|_, _, _| Err(()));
+            .returning(|_, _, _| Ok("2\t5\n".to_string()));
 
-        let result = super::unstash(&command, false);
+        assert_eq!(
+            super::ahead_behind(&command, "feature", "main", false),
+            Ok((2, 5))
+        );
+    }
 
-        assert!(result.is_err());
+    #[test]
+    fn test_format_date() {
+        assert_eq!(super::format_date(0), "1970-01-01");
+        assert_eq!(super::format_date(951_782_400), "2000-02-29");
+        assert_eq!(super::format_date(1_792_368_000), "2026-10-19");
+    }
+
+    #[test]
+    fn test_parse_days() {
+        assert_eq!(super::parse_days("30d"), Ok(30));
+        assert_eq!(super::parse_days("2w"), Ok(14));
+        assert_eq!(super::parse_days("6m"), Ok(180));
+        assert_eq!(super::parse_days("1y"), Ok(365));
+        assert_eq!(super::parse_days("12"), Ok(12));
+    }
+
+    #[test]
+    fn test_parse_days_invalid() {
+        assert!(super::parse_days("").is_err());
+        assert!(super::parse_days("d").is_err());
+        assert!(super::parse_days("3h").is_err());
     }
 }